@@ -1,7 +1,8 @@
 use crate::profiles::Profile;
 use crate::{
-    CT_ACCEPT_INVALID_CERTS, CT_API_KEY, CT_ENVIRONMENT, CT_PROJECT, CT_REQ_TIMEOUT, CT_REST_DEBUG,
-    CT_REST_PAGE_SIZE, CT_REST_SUCCESS, CT_SERVER_URL,
+    CT_ACCEPT_INVALID_CERTS, CT_API_KEY, CT_ENCRYPTION_KEY_FILE, CT_ENVIRONMENT, CT_OFFLINE_CACHE,
+    CT_OFFLINE_CACHE_TTL, CT_PROJECT, CT_REQ_TIMEOUT, CT_REST_DEBUG, CT_REST_PAGE_SIZE,
+    CT_REST_SUCCESS, CT_SERVER_URL,
 };
 use std::env;
 
@@ -21,6 +22,13 @@ impl ConfigEnv {
             server_url: Self::get_override(CT_SERVER_URL),
             source_profile: None,
             accept_invalid_certs: Self::get_accept_invalid_certs(),
+            encryption_key_file: Self::get_override(CT_ENCRYPTION_KEY_FILE),
+            key_prefix: None,
+            key_strip_prefix: None,
+            key_uppercase: None,
+            key_sanitize: None,
+            offline_cache: Self::get_offline_cache(),
+            offline_cache_ttl: Self::get_offline_cache_ttl(),
         }
     }
 
@@ -54,6 +62,15 @@ impl ConfigEnv {
             .map(|e| matches!(e.to_lowercase().as_str(), "true" | "1" | "yes"))
     }
 
+    pub fn get_offline_cache() -> Option<bool> {
+        Self::get_override(CT_OFFLINE_CACHE)
+            .map(|e| matches!(e.to_lowercase().as_str(), "true" | "1" | "yes"))
+    }
+
+    pub fn get_offline_cache_ttl() -> Option<u64> {
+        Self::get_override(CT_OFFLINE_CACHE_TTL).and_then(|e| e.trim().parse().ok())
+    }
+
     pub fn get_rest_success() -> Vec<String> {
         let mut result = vec![];
         if let Some(env_str) = Self::get_override(CT_REST_SUCCESS) {
@@ -91,6 +108,8 @@ mod tests {
         env::remove_var(CT_REST_SUCCESS);
         env::remove_var(CT_REST_PAGE_SIZE);
         env::remove_var(CT_ACCEPT_INVALID_CERTS);
+        env::remove_var(CT_OFFLINE_CACHE);
+        env::remove_var(CT_OFFLINE_CACHE_TTL);
     }
 
     #[test]
@@ -112,6 +131,8 @@ mod tests {
         env::set_var(CT_REST_DEBUG, "true");
         env::set_var(CT_REST_SUCCESS, "sna,foo,bar");
         env::set_var(CT_ACCEPT_INVALID_CERTS, "1");
+        env::set_var(CT_OFFLINE_CACHE, "yes");
+        env::set_var(CT_OFFLINE_CACHE_TTL, "600");
 
         assert_eq!(
             Profile {
@@ -125,7 +146,14 @@ mod tests {
                 rest_page_size: None,
                 server_url: Some("http://localhost:7001/graphql".to_string()),
                 source_profile: None,
-                accept_invalid_certs: Some(true)
+                accept_invalid_certs: Some(true),
+                encryption_key_file: None,
+                key_prefix: None,
+                key_strip_prefix: None,
+                key_uppercase: None,
+                key_sanitize: None,
+                offline_cache: Some(true),
+                offline_cache_ttl: Some(600),
             },
             ConfigEnv::load_profile()
         );
@@ -135,6 +135,13 @@ impl ConfigFile {
             server_url: None,
             source_profile: source.map(String::from),
             accept_invalid_certs: None,
+            encryption_key_file: None,
+            key_prefix: None,
+            key_strip_prefix: None,
+            key_uppercase: None,
+            key_sanitize: None,
+            offline_cache: None,
+            offline_cache_ttl: None,
         };
 
         let profiles = config_file.profiles.borrow_mut();
@@ -189,6 +196,13 @@ impl ConfigFile {
             rest_success: profile.rest_success.clone(),
             rest_page_size: profile.rest_page_size,
             accept_invalid_certs: profile.accept_invalid_certs,
+            encryption_key_file: profile.encryption_key_file.clone(),
+            key_prefix: profile.key_prefix.clone(),
+            key_strip_prefix: profile.key_strip_prefix.clone(),
+            key_uppercase: profile.key_uppercase,
+            key_sanitize: profile.key_sanitize,
+            offline_cache: profile.offline_cache,
+            offline_cache_ttl: profile.offline_cache_ttl,
         }
     }
 
@@ -727,7 +741,14 @@ mod tests {
                 rest_success: vec![],
                 rest_page_size: None,
                 request_timeout: None,
-                accept_invalid_certs: None
+                accept_invalid_certs: None,
+                encryption_key_file: None,
+                key_prefix: None,
+                key_strip_prefix: None,
+                key_uppercase: None,
+                key_sanitize: None,
+                offline_cache: None,
+                offline_cache_ttl: None,
             },
         );
     }
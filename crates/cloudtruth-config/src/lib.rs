@@ -28,6 +28,9 @@ pub const DEFAULT_SERVER_URL: &str = "https://api.cloudtruth.io";
 /// Default OpenApi server request timeout
 pub const DEFAULT_REQUEST_TIMEOUT: u64 = 90;
 
+/// Default time (in seconds) that offline cache entries can be used
+pub const DEFAULT_OFFLINE_CACHE_TTL: u64 = 86400;
+
 /// Default environment name.
 pub const DEFAULT_ENV_NAME: &str = "default";
 
@@ -68,6 +71,18 @@ pub const CT_REST_PAGE_SIZE: &str = "CLOUDTRUTH_REST_PAGE_SIZE";
 /// Environment variable name used to trust any certificate from the server
 pub const CT_ACCEPT_INVALID_CERTS: &str = "CLOUDTRUTH_ACCEPT_INVALID_CERTS";
 
+/// Environment variable name used to set the key file for client-side encrypted parameter values.
+pub const CT_ENCRYPTION_KEY_FILE: &str = "CLOUDTRUTH_ENCRYPTION_KEY_FILE";
+
+/// Environment variable name used to enable the encrypted offline cache of parameter values.
+pub const CT_OFFLINE_CACHE: &str = "CLOUDTRUTH_OFFLINE_CACHE";
+
+/// Environment variable name used to set how long (in seconds) offline cache entries can be used.
+pub const CT_OFFLINE_CACHE_TTL: &str = "CLOUDTRUTH_OFFLINE_CACHE_TTL";
+
+/// Environment variable name used to tell a `run` command where its rendered templates are.
+pub const CT_TEMPLATE_DIR: &str = "CLOUDTRUTH_TEMPLATE_DIR";
+
 /// List of variables to remove to make a clean environment.
 #[allow(dead_code)]
 pub const CT_APP_REMOVABLE_VARS: &[&str] = &[CT_SERVER_URL, CT_API_KEY];
@@ -104,6 +119,13 @@ pub struct Config {
     pub rest_page_size: Option<i32>,
     pub rest_success: Vec<String>,
     pub accept_invalid_certs: Option<bool>,
+    pub encryption_key_file: Option<String>,
+    pub key_prefix: Option<String>,
+    pub key_strip_prefix: Option<String>,
+    pub key_uppercase: bool,
+    pub key_sanitize: bool,
+    pub offline_cache: bool,
+    pub offline_cache_ttl: Duration,
 }
 
 pub struct ValidationError {
@@ -136,6 +158,17 @@ fn profile_into_config(prof_name: &str, profile: &Profile) -> Config {
         rest_success: profile.rest_success.clone(),
         rest_page_size: profile.rest_page_size,
         accept_invalid_certs: profile.accept_invalid_certs,
+        encryption_key_file: profile.encryption_key_file.clone(),
+        key_prefix: profile.key_prefix.clone(),
+        key_strip_prefix: profile.key_strip_prefix.clone(),
+        key_uppercase: profile.key_uppercase.unwrap_or(false),
+        key_sanitize: profile.key_sanitize.unwrap_or(false),
+        offline_cache: profile.offline_cache.unwrap_or(false),
+        offline_cache_ttl: Duration::from_secs(
+            profile
+                .offline_cache_ttl
+                .unwrap_or(DEFAULT_OFFLINE_CACHE_TTL),
+        ),
     }
 }
 
@@ -155,6 +188,9 @@ pub const PARAM_REST_DEBUG: &str = "REST debug";
 pub const PARAM_ACCEPT_INVALID_CERTS: &str = "Accept Invalid Certs";
 pub const PARAM_REST_SUCCESS: &str = "REST success";
 pub const PARAM_REST_PAGE_SIZE: &str = "REST page size";
+pub const PARAM_ENCRYPTION_KEY_FILE: &str = "Encryption key file";
+pub const PARAM_OFFLINE_CACHE: &str = "Offline cache";
+pub const PARAM_OFFLINE_CACHE_TTL: &str = "Offline cache TTL";
 pub const PARAM_CLI_VERSION: &str = "CLI version";
 pub const PARAM_USER: &str = "User";
 pub const PARAM_ROLE: &str = "Role";
@@ -176,6 +212,18 @@ impl Config {
             .map(|project_dirs| project_dirs.config_dir().join(CONFIG_FILE_NAME))
     }
 
+    /// Directory for the encrypted offline cache files.
+    pub fn cache_dir() -> Option<PathBuf> {
+        ProjectDirs::from("com", ORGANIZATION_NAME, APPLICATION_NAME)
+            .map(|project_dirs| project_dirs.config_dir().join("cache"))
+    }
+
+    /// File recording the project files the shell hook is allowed to load.
+    pub fn shell_hook_allowed_file() -> Option<PathBuf> {
+        ProjectDirs::from("com", ORGANIZATION_NAME, APPLICATION_NAME)
+            .map(|project_dirs| project_dirs.config_dir().join("shell-hook-allowed.json"))
+    }
+
     pub fn filename() -> String {
         Config::config_file()
             .unwrap()
@@ -675,6 +723,78 @@ impl Config {
             extension: true,
         });
 
+        //////////////////
+        // Encryption key file
+        let mut value = "".to_string();
+        let mut source = SRC_DEFAULT.to_string();
+        if let Some(env_value) = ConfigEnv::get_override(CT_ENCRYPTION_KEY_FILE) {
+            value = env_value;
+            source = SRC_ENV.to_string();
+        } else {
+            for profile in &profiles {
+                if let Some(ref prof_value) = profile.encryption_key_file {
+                    value = prof_value.clone();
+                    source = format!("{} ({})", SRC_PROFILE, profile.name);
+                    break;
+                }
+            }
+        }
+        results.push(ConfigValue {
+            name: PARAM_ENCRYPTION_KEY_FILE.to_string(),
+            value,
+            source,
+            secret: false,
+            extension: true,
+        });
+
+        //////////////////
+        // Offline cache
+        let mut value = "false".to_string();
+        let mut source = SRC_DEFAULT.to_string();
+        if let Some(env_value) = ConfigEnv::get_offline_cache() {
+            value = env_value.to_string();
+            source = SRC_ENV.to_string();
+        } else {
+            for profile in &profiles {
+                if let Some(ref prof_value) = profile.offline_cache {
+                    value = prof_value.to_string();
+                    source = format!("{} ({})", SRC_PROFILE, profile.name);
+                    break;
+                }
+            }
+        }
+        results.push(ConfigValue {
+            name: PARAM_OFFLINE_CACHE.to_string(),
+            value,
+            source,
+            secret: false,
+            extension: true,
+        });
+
+        //////////////////
+        // Offline cache TTL
+        let mut value = format!("{DEFAULT_OFFLINE_CACHE_TTL}");
+        let mut source = SRC_DEFAULT.to_string();
+        if let Some(env_value) = ConfigEnv::get_offline_cache_ttl() {
+            value = env_value.to_string();
+            source = SRC_ENV.to_string();
+        } else {
+            for profile in &profiles {
+                if let Some(ref prof_value) = profile.offline_cache_ttl {
+                    value = prof_value.to_string();
+                    source = format!("{} ({})", SRC_PROFILE, profile.name);
+                    break;
+                }
+            }
+        }
+        results.push(ConfigValue {
+            name: PARAM_OFFLINE_CACHE_TTL.to_string(),
+            value,
+            source,
+            secret: false,
+            extension: true,
+        });
+
         Ok(results)
     }
 
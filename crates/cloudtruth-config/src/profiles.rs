@@ -25,6 +25,20 @@ pub struct Profile {
     pub source_profile: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub accept_invalid_certs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption_key_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_strip_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_uppercase: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_sanitize: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline_cache: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline_cache_ttl: Option<u64>,
 }
 
 // TODO: Rick Porter 4/21, fix this so don't have to udpate when Profile is updated
@@ -42,6 +56,13 @@ pub struct ProfileDetails {
     pub rest_success: Vec<String>,
     pub rest_page_size: Option<i32>,
     pub accept_invalid_certs: Option<bool>,
+    pub encryption_key_file: Option<String>,
+    pub key_prefix: Option<String>,
+    pub key_strip_prefix: Option<String>,
+    pub key_uppercase: Option<bool>,
+    pub key_sanitize: Option<bool>,
+    pub offline_cache: Option<bool>,
+    pub offline_cache_ttl: Option<u64>,
 }
 
 fn empty_to_none(value: &Option<String>) -> Option<String> {
@@ -79,6 +100,19 @@ impl Profile {
             server_url: other.server_url.clone().or_else(|| self.server_url.clone()),
             source_profile: self.source_profile.clone(),
             accept_invalid_certs: other.accept_invalid_certs.or(self.accept_invalid_certs),
+            encryption_key_file: other
+                .encryption_key_file
+                .clone()
+                .or_else(|| self.encryption_key_file.clone()),
+            key_prefix: other.key_prefix.clone().or_else(|| self.key_prefix.clone()),
+            key_strip_prefix: other
+                .key_strip_prefix
+                .clone()
+                .or_else(|| self.key_strip_prefix.clone()),
+            key_uppercase: other.key_uppercase.or(self.key_uppercase),
+            key_sanitize: other.key_sanitize.or(self.key_sanitize),
+            offline_cache: other.offline_cache.or(self.offline_cache),
+            offline_cache_ttl: other.offline_cache_ttl.or(self.offline_cache_ttl),
         }
     }
 
@@ -96,6 +130,13 @@ impl Profile {
             server_url: empty_to_none(&self.server_url),
             source_profile: empty_to_none(&self.source_profile),
             accept_invalid_certs: self.accept_invalid_certs,
+            encryption_key_file: empty_to_none(&self.encryption_key_file),
+            key_prefix: empty_to_none(&self.key_prefix),
+            key_strip_prefix: empty_to_none(&self.key_strip_prefix),
+            key_uppercase: self.key_uppercase,
+            key_sanitize: self.key_sanitize,
+            offline_cache: self.offline_cache,
+            offline_cache_ttl: self.offline_cache_ttl,
         }
     }
 
@@ -110,6 +151,13 @@ impl Profile {
             && self.server_url.is_none()
             && self.source_profile.is_none()
             && self.accept_invalid_certs.is_none()
+            && self.encryption_key_file.is_none()
+            && self.key_prefix.is_none()
+            && self.key_strip_prefix.is_none()
+            && self.key_uppercase.is_none()
+            && self.key_sanitize.is_none()
+            && self.offline_cache.is_none()
+            && self.offline_cache_ttl.is_none()
     }
 }
 
@@ -148,6 +196,13 @@ mod tests {
             request_timeout: Some(23),
             rest_debug: Some(false),
             accept_invalid_certs: Some(true),
+            encryption_key_file: Some("/tmp/secret.key".to_string()),
+            key_prefix: Some("APP_".to_string()),
+            key_strip_prefix: Some("legacy.".to_string()),
+            key_uppercase: Some(true),
+            key_sanitize: Some(false),
+            offline_cache: Some(true),
+            offline_cache_ttl: Some(3600),
             rest_success: vec!["proj".to_string(), "env".to_string()],
             rest_page_size: Some(500),
             server_url: Some("http://localhost:7001/graphql".to_string()),
@@ -211,6 +266,24 @@ mod tests {
             ..Profile::default()
         };
         assert!(!prof.is_empty());
+
+        let prof = Profile {
+            encryption_key_file: Some("key-file".to_string()),
+            ..Profile::default()
+        };
+        assert!(!prof.is_empty());
+
+        let prof = Profile {
+            key_uppercase: Some(false),
+            ..Profile::default()
+        };
+        assert!(!prof.is_empty());
+
+        let prof = Profile {
+            offline_cache: Some(false),
+            ..Profile::default()
+        };
+        assert!(!prof.is_empty());
     }
 
     #[test]
@@ -227,6 +300,13 @@ mod tests {
             server_url: Some("".to_string()),
             source_profile: Some("".to_string()),
             accept_invalid_certs: None,
+            encryption_key_file: None,
+            key_prefix: Some("".to_string()),
+            key_strip_prefix: Some("".to_string()),
+            key_uppercase: None,
+            key_sanitize: None,
+            offline_cache: None,
+            offline_cache_ttl: None,
         };
 
         let prof2 = prof.remove_empty();
@@ -245,6 +325,13 @@ mod tests {
             server_url: Some("url".to_string()),
             source_profile: Some("src-prof".to_string()),
             accept_invalid_certs: None,
+            encryption_key_file: None,
+            key_prefix: None,
+            key_strip_prefix: None,
+            key_uppercase: None,
+            key_sanitize: None,
+            offline_cache: None,
+            offline_cache_ttl: None,
         };
         let prof2 = prof.remove_empty();
         assert_eq!(prof, prof2);
@@ -1,10 +1,1401 @@
-use crate::cli::{CONFIRM_FLAG, FORMAT_OPT};
-use crate::database::{Backups, OpenApiConfig};
-use crate::utils::{error_message, user_confirm, warn_missing_subcommand, warning_message};
+use crate::cli::{CONFIRM_FLAG, DIFF_SUBCMD, FORMAT_OPT, KEY_FILE_OPT, SECRETS_FLAG};
+use crate::database::{
+    BackupManifest, BackupSnapshotDetails, Backups, CryptoAlgorithm, CryptoEnvelope, CryptoKdf,
+    EnvironmentDetails, Environments, OpenApiConfig, ParamRuleType, ParameterDetailMap,
+    ParameterDetails, ParameterRuleDetail, Parameters, ProjectDetails, Projects, TemplateDetails,
+    Templates, TypeDetails, Types,
+};
+use crate::run_templates::write_private;
+use crate::table::Table;
+use crate::utils::{
+    error_message, user_confirm, warn_missing_subcommand, warning_message, FILE_READ_ERR, REDACTED,
+};
 use clap::ArgMatches;
+use cloudtruth_config::DEFAULT_ENV_NAME;
+use cloudtruth_restapi::models::{
+    BackupEnvironment, BackupParameter, BackupParameterRule, BackupParameterType,
+    BackupParameterValue, BackupProject, BackupTemplate,
+};
 use color_eyre::eyre::Result;
 use indoc::printdoc;
+use rpassword::read_password;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+
+/// The algorithm, key derivation and key material used to encrypt backup files (if any).
+type Encryption = Option<(CryptoAlgorithm, CryptoKdf, Vec<u8>)>;
+
+const RESTORE_CREATE: &str = "create";
+const RESTORE_UPDATE: &str = "update";
+const RESTORE_UNCHANGED: &str = "unchanged";
+const DIFF_ADDED: &str = "added";
+const DIFF_REMOVED: &str = "removed";
+const DIFF_CHANGED: &str = "changed";
+
+/// Describes a single object that was (or would be, when previewing) restored from a snapshot, or
+/// that differs between two snapshots.
+#[derive(Debug, Default)]
+pub struct RestoreDetails {
+    pub object_type: String,
+    pub name: String,
+    pub value: String,
+    pub action: String,
+    pub project: String,
+    pub environment: String,
+}
+
+impl RestoreDetails {
+    pub fn get_property(&self, property_name: &str) -> String {
+        match property_name {
+            "type" => self.object_type.clone(),
+            "name" => self.name.clone(),
+            "value" => self.value.clone(),
+            "action" => self.action.clone(),
+            "project" => self.project.clone(),
+            "environment" => self.environment.clone(),
+            _ => format!("Unhandled property name '{property_name}'"),
+        }
+    }
+
+    pub fn get_properties(&self, fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|p| self.get_property(p)).collect()
+    }
+}
+
+/// A rule from the snapshot that needs to be compared against the existing rules.
+struct RuleChange {
+    rule_type: ParamRuleType,
+    constraint: String,
+    rule_id: Option<String>,
+    action: &'static str,
+}
+
+/// Orders the `(name, parent)` pairs such that every parent comes before its children.
+///
+/// Parents that are not in the list are assumed to already exist.
+fn dependency_order(items: &[(String, Option<String>)]) -> Vec<String> {
+    let names: HashSet<&str> = items.iter().map(|(n, _)| n.as_str()).collect();
+    let mut remaining: Vec<&(String, Option<String>)> = items.iter().collect();
+    let mut ordered: Vec<String> = vec![];
+    remaining.sort_by(|l, r| l.0.cmp(&r.0));
+    while !remaining.is_empty() {
+        let before = remaining.len();
+        remaining.retain(|(name, parent)| {
+            let ready = match parent {
+                Some(p) if names.contains(p.as_str()) && p != name => ordered.contains(p),
+                _ => true,
+            };
+            if ready {
+                ordered.push(name.clone());
+            }
+            !ready
+        });
+        if remaining.len() == before {
+            // circular parentage -- add what is left, and let the server complain
+            ordered.extend(remaining.iter().map(|(n, _)| n.clone()));
+            break;
+        }
+    }
+    ordered
+}
+
+/// Compares the snapshot rules against the `existing` rules.
+fn rule_changes(existing: &[ParameterRuleDetail], rules: &[(String, String)]) -> Vec<RuleChange> {
+    let mut result: Vec<RuleChange> = vec![];
+    for (rule_name, constraint) in rules {
+        let rule_type = match ParamRuleType::from_str(rule_name) {
+            Ok(rule_type) => rule_type,
+            Err(_) => {
+                warning_message(format!("Skipping unknown rule type '{rule_name}'"));
+                continue;
+            }
+        };
+        let (rule_id, action) = match existing.iter().find(|r| r.rule_type == rule_type) {
+            None => (None, RESTORE_CREATE),
+            Some(r) if &r.constraint == constraint => (Some(r.id.clone()), RESTORE_UNCHANGED),
+            Some(r) => (Some(r.id.clone()), RESTORE_UPDATE),
+        };
+        result.push(RuleChange {
+            rule_type,
+            constraint: constraint.clone(),
+            rule_id,
+            action,
+        });
+    }
+    result
+}
+
+/// Restores the environments, and returns a map of environment names to identifiers.
+///
+/// When previewing, environments that do not exist yet are not in the returned map.
+fn restore_environments(
+    rest_cfg: &OpenApiConfig,
+    snapshot: &BackupSnapshotDetails,
+    preview: bool,
+    changes: &mut Vec<RestoreDetails>,
+) -> Result<HashMap<String, String>> {
+    let environments = Environments::new();
+    let mut existing: HashMap<String, EnvironmentDetails> = HashMap::new();
+    let mut env_ids: HashMap<String, String> = HashMap::new();
+    let mut env_urls: HashMap<String, String> = HashMap::new();
+    for details in environments.get_environment_details(rest_cfg)? {
+        env_ids.insert(details.name.clone(), details.id.clone());
+        env_urls.insert(details.name.clone(), details.url.clone());
+        existing.insert(details.name.clone(), details);
+    }
+
+    let by_name: HashMap<&str, _> = snapshot
+        .environments
+        .values()
+        .map(|e| (e.name.as_str(), e))
+        .collect();
+    let items: Vec<(String, Option<String>)> = by_name
+        .values()
+        .map(|e| (e.name.clone(), e.parent.clone()))
+        .collect();
+    for env_name in dependency_order(&items) {
+        let env = by_name[env_name.as_str()];
+        let description = env.description.clone().unwrap_or_default();
+        let action = if let Some(details) = existing.get(&env_name) {
+            if details.description == description {
+                RESTORE_UNCHANGED
+            } else {
+                if !preview {
+                    environments.update_environment(
+                        rest_cfg,
+                        &details.id,
+                        &env_name,
+                        Some(&description),
+                    )?;
+                }
+                RESTORE_UPDATE
+            }
+        } else {
+            if !preview {
+                let parent_name = env.parent.as_deref().unwrap_or(DEFAULT_ENV_NAME);
+                let parent_url = match env_urls.get(parent_name) {
+                    Some(url) => url.clone(),
+                    None => {
+                        error_message(format!("No parent environment '{parent_name}' found"));
+                        process::exit(57);
+                    }
+                };
+                environments.create_environment(
+                    rest_cfg,
+                    &env_name,
+                    env.description.as_deref(),
+                    &parent_url,
+                )?;
+                if let Some(details) = environments.get_details_by_name(rest_cfg, &env_name)? {
+                    env_ids.insert(details.name.clone(), details.id);
+                    env_urls.insert(details.name, details.url);
+                }
+            }
+            RESTORE_CREATE
+        };
+        changes.push(RestoreDetails {
+            object_type: "environment".to_string(),
+            name: env_name.clone(),
+            action: action.to_string(),
+            environment: env_name,
+            ..Default::default()
+        });
+    }
+    Ok(env_ids)
+}
+
+/// Restores the parameter types, and their rules.
+fn restore_types(
+    rest_cfg: &OpenApiConfig,
+    snapshot: &BackupSnapshotDetails,
+    preview: bool,
+    changes: &mut Vec<RestoreDetails>,
+) -> Result<()> {
+    let types = Types::new();
+    let mut existing: HashMap<String, TypeDetails> = HashMap::new();
+    let mut type_urls: HashMap<String, String> = HashMap::new();
+    for details in types.get_type_details(rest_cfg)? {
+        type_urls.insert(details.name.clone(), details.url.clone());
+        existing.insert(details.name.clone(), details);
+    }
+
+    let by_name: HashMap<&str, _> = snapshot
+        .types
+        .values()
+        .map(|t| (t.name.as_str(), t))
+        .collect();
+    let items: Vec<(String, Option<String>)> = by_name
+        .values()
+        .map(|t| (t.name.clone(), Some(t.parent.clone())))
+        .collect();
+    for type_name in dependency_order(&items) {
+        let param_type = by_name[type_name.as_str()];
+        // the built-in types are the only ones without a parent, and cannot be modified
+        if param_type.parent.is_empty() {
+            continue;
+        }
+
+        let description = param_type.description.clone().unwrap_or_default();
+        let mut type_id: Option<String> = None;
+        let mut current_rules: Vec<ParameterRuleDetail> = vec![];
+        let action = if let Some(details) = existing.get(&type_name) {
+            type_id = Some(details.id.clone());
+            current_rules = details.rules.clone();
+            if details.description == description {
+                RESTORE_UNCHANGED
+            } else {
+                if !preview {
+                    types.update_type(
+                        rest_cfg,
+                        &type_name,
+                        &details.id,
+                        Some(&description),
+                        None,
+                    )?;
+                }
+                RESTORE_UPDATE
+            }
+        } else {
+            if !preview {
+                let parent_name = &param_type.parent;
+                let parent_url = match type_urls.get(parent_name) {
+                    Some(url) => url.clone(),
+                    None => {
+                        error_message(format!("No parent parameter type '{parent_name}' found"));
+                        process::exit(57);
+                    }
+                };
+                let details = types.create_type(
+                    rest_cfg,
+                    &type_name,
+                    param_type.description.as_deref(),
+                    &parent_url,
+                )?;
+                type_id = Some(details.id.clone());
+                type_urls.insert(details.name.clone(), details.url);
+            }
+            RESTORE_CREATE
+        };
+        changes.push(RestoreDetails {
+            object_type: "parameter-type".to_string(),
+            name: type_name.clone(),
+            action: action.to_string(),
+            ..Default::default()
+        });
+
+        let rules: Vec<(String, String)> = param_type
+            .rules
+            .values()
+            .map(|r| (r.rule_type.clone(), r.constraint.clone()))
+            .collect();
+        for rule in rule_changes(&current_rules, &rules) {
+            if let (false, Some(type_id)) = (preview, &type_id) {
+                match (rule.action, &rule.rule_id) {
+                    (RESTORE_CREATE, _) => {
+                        types.create_type_rule(
+                            rest_cfg,
+                            type_id,
+                            rule.rule_type,
+                            &rule.constraint,
+                        )?;
+                    }
+                    (RESTORE_UPDATE, Some(rule_id)) => {
+                        types.update_type_rule(
+                            rest_cfg,
+                            type_id,
+                            rule_id,
+                            None,
+                            Some(&rule.constraint),
+                        )?;
+                    }
+                    _ => {}
+                }
+            }
+            changes.push(RestoreDetails {
+                object_type: "parameter-type-rule".to_string(),
+                name: type_name.clone(),
+                value: format!("{}: {}", rule.rule_type, rule.constraint),
+                action: rule.action.to_string(),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Restores the projects, and returns a map of project names to identifiers.
+///
+/// When previewing, projects that do not exist yet are not in the returned map.
+fn restore_projects(
+    rest_cfg: &OpenApiConfig,
+    snapshot: &BackupSnapshotDetails,
+    preview: bool,
+    changes: &mut Vec<RestoreDetails>,
+) -> Result<(Vec<String>, HashMap<String, String>)> {
+    let projects = Projects::new();
+    let mut existing: HashMap<String, ProjectDetails> = HashMap::new();
+    let mut proj_ids: HashMap<String, String> = HashMap::new();
+    let mut proj_urls: HashMap<String, String> = HashMap::new();
+    for details in projects.get_project_details(rest_cfg)? {
+        proj_ids.insert(details.name.clone(), details.id.clone());
+        proj_urls.insert(details.name.clone(), details.url.clone());
+        existing.insert(details.name.clone(), details);
+    }
+
+    let items: Vec<(String, Option<String>)> = snapshot
+        .projects
+        .values()
+        .map(|p| (p.name.clone(), p.parent.clone()))
+        .collect();
+    let order = dependency_order(&items);
+    for proj_name in &order {
+        let project = snapshot
+            .projects
+            .values()
+            .find(|p| &p.name == proj_name)
+            .unwrap();
+        let description = project.description.clone().unwrap_or_default();
+        let parent_name = project.parent.clone().unwrap_or_default();
+        let parent_url = match parent_name.is_empty() {
+            true => Some(String::new()),
+            false => proj_urls.get(&parent_name).cloned(),
+        };
+        if parent_url.is_none() && !preview {
+            error_message(format!("No parent project '{parent_name}' found"));
+            process::exit(57);
+        }
+
+        let action = if let Some(details) = existing.get(proj_name) {
+            if details.description == description && details.parent_name == parent_name {
+                RESTORE_UNCHANGED
+            } else {
+                if !preview {
+                    projects.update_project(
+                        rest_cfg,
+                        proj_name,
+                        &details.id,
+                        Some(&description),
+                        parent_url.as_deref(),
+                        None,
+                    )?;
+                }
+                RESTORE_UPDATE
+            }
+        } else {
+            if !preview {
+                projects.create_project(
+                    rest_cfg,
+                    proj_name,
+                    project.description.as_deref(),
+                    parent_url.as_deref().filter(|u| !u.is_empty()),
+                    None,
+                )?;
+                if let Some(details) = projects.get_details_by_name(rest_cfg, proj_name, false)? {
+                    proj_ids.insert(details.name.clone(), details.id);
+                    proj_urls.insert(details.name, details.url);
+                }
+            }
+            RESTORE_CREATE
+        };
+        changes.push(RestoreDetails {
+            object_type: "project".to_string(),
+            name: proj_name.clone(),
+            action: action.to_string(),
+            project: proj_name.clone(),
+            ..Default::default()
+        });
+    }
+    Ok((order, proj_ids))
+}
+
+/// Restores the parameters (with rules and values) of the projects in the specified `order`.
+#[allow(clippy::too_many_arguments)]
+fn restore_parameters(
+    rest_cfg: &OpenApiConfig,
+    snapshot: &BackupSnapshotDetails,
+    order: &[String],
+    proj_ids: &HashMap<String, String>,
+    env_ids: &HashMap<String, String>,
+    preview: bool,
+    show_secrets: bool,
+    changes: &mut Vec<RestoreDetails>,
+) -> Result<()> {
+    let parameters = Parameters::new();
+    for proj_name in order {
+        let project = snapshot
+            .projects
+            .values()
+            .find(|p| &p.name == proj_name)
+            .unwrap();
+        let proj_id = proj_ids.get(proj_name);
+        let mut existing: HashMap<String, ParameterDetails> = HashMap::new();
+        if let Some(proj_id) = proj_id {
+            for details in parameters
+                .get_parameter_details(rest_cfg, proj_id, "", true, false, false, None, None)?
+            {
+                existing.insert(details.key.clone(), details);
+            }
+        }
+        // values are fetched per-environment, and only when needed
+        let mut env_values: HashMap<String, ParameterDetailMap> = HashMap::new();
+
+        let mut param_list: Vec<_> = project
+            .parameters
+            .values()
+            // parameters inherited from a parent project get restored with the parent
+            .filter(|p| &p.project == proj_name)
+            .collect();
+        param_list.sort_by(|l, r| l.name.cmp(&r.name));
+        for param in param_list {
+            let key = &param.name;
+            let description = param.description.clone().unwrap_or_default();
+            let mut param_id: Option<String> = None;
+            let mut current_rules: Vec<ParameterRuleDetail> = vec![];
+            let action = if let Some(details) = existing.get(key) {
+                param_id = Some(details.id.clone());
+                current_rules = details.rules.clone();
+                if details.description == description
+                    && details.secret == param.secret
+                    && details.param_type == param.param_type
+                {
+                    RESTORE_UNCHANGED
+                } else {
+                    if !preview {
+                        parameters.update_parameter(
+                            rest_cfg,
+                            proj_id.unwrap(),
+                            &details.id,
+                            key,
+                            Some(&description),
+                            Some(param.secret),
+                            Some(&param.param_type),
+                        )?;
+                    }
+                    RESTORE_UPDATE
+                }
+            } else {
+                if let (false, Some(proj_id)) = (preview, proj_id) {
+                    let details = parameters.create_parameter(
+                        rest_cfg,
+                        proj_id,
+                        key,
+                        param.description.as_deref(),
+                        Some(param.secret),
+                        Some(&param.param_type),
+                    )?;
+                    param_id = Some(details.id);
+                }
+                RESTORE_CREATE
+            };
+            changes.push(RestoreDetails {
+                object_type: "parameter".to_string(),
+                name: key.clone(),
+                action: action.to_string(),
+                project: proj_name.clone(),
+                ..Default::default()
+            });
+
+            let rules: Vec<(String, String)> = param
+                .rules
+                .values()
+                .map(|r| (r.rule_type.clone(), r.constraint.clone()))
+                .collect();
+            for rule in rule_changes(&current_rules, &rules) {
+                if let (false, Some(proj_id), Some(param_id)) = (preview, proj_id, &param_id) {
+                    match (rule.action, &rule.rule_id) {
+                        (RESTORE_CREATE, _) => {
+                            parameters.create_parameter_rule(
+                                rest_cfg,
+                                proj_id,
+                                param_id,
+                                rule.rule_type,
+                                &rule.constraint,
+                            )?;
+                        }
+                        (RESTORE_UPDATE, Some(rule_id)) => {
+                            parameters.update_parameter_rule(
+                                rest_cfg,
+                                proj_id,
+                                param_id,
+                                rule_id,
+                                None,
+                                Some(&rule.constraint),
+                            )?;
+                        }
+                        _ => {}
+                    }
+                }
+                changes.push(RestoreDetails {
+                    object_type: "parameter-rule".to_string(),
+                    name: key.clone(),
+                    value: format!("{}: {}", rule.rule_type, rule.constraint),
+                    action: rule.action.to_string(),
+                    project: proj_name.clone(),
+                    ..Default::default()
+                });
+            }
+
+            let mut value_list: Vec<_> = param
+                .values
+                .values()
+                // values inherited from a parent environment get restored with the parent
+                .filter(|v| v.source.is_none() || v.source.as_ref() == Some(&v.environment))
+                .collect();
+            value_list.sort_by(|l, r| l.environment.cmp(&r.environment));
+            for value in value_list {
+                let env_name = &value.environment;
+                let env_id = env_ids.get(env_name);
+                if let (Some(proj_id), Some(env_id)) = (proj_id, env_id) {
+                    if !env_values.contains_key(env_name) {
+                        let value_map = parameters.get_parameter_detail_map(
+                            rest_cfg, proj_id, env_id, true, false, None, None,
+                        )?;
+                        env_values.insert(env_name.clone(), value_map);
+                    }
+                }
+                let current = env_values
+                    .get(env_name)
+                    .and_then(|m| m.get(key))
+                    .filter(|d| &d.env_name == env_name && !d.val_id.is_empty());
+
+                let fqn = value.external.as_ref().map(|e| e.fqn.clone());
+                let jmes_path = value.external.as_ref().and_then(|e| e.jmes_path.clone());
+                let raw = match fqn {
+                    Some(_) => None,
+                    None => Some(
+                        value
+                            .raw
+                            .clone()
+                            .or_else(|| value.value.clone())
+                            .unwrap_or_default(),
+                    ),
+                };
+                let action = match current {
+                    None => {
+                        if let (false, Some(proj_id), Some(env_id), Some(param_id)) =
+                            (preview, proj_id, env_id, &param_id)
+                        {
+                            parameters.create_parameter_value(
+                                rest_cfg,
+                                proj_id,
+                                env_id,
+                                param_id,
+                                raw.as_deref(),
+                                fqn.as_deref(),
+                                jmes_path.as_deref(),
+                                Some(value.evaluated),
+                            )?;
+                        }
+                        RESTORE_CREATE
+                    }
+                    Some(details) => {
+                        let same = match &fqn {
+                            Some(fqn) => {
+                                details.external
+                                    && &details.fqn == fqn
+                                    && details.jmes_path == jmes_path.clone().unwrap_or_default()
+                            }
+                            None => {
+                                !details.external
+                                    && Some(&details.raw_value) == raw.as_ref()
+                                    && details.evaluated == value.evaluated
+                            }
+                        };
+                        if same {
+                            RESTORE_UNCHANGED
+                        } else {
+                            if !preview {
+                                parameters.update_parameter_value(
+                                    rest_cfg,
+                                    proj_id.unwrap(),
+                                    &details.id,
+                                    &details.val_id,
+                                    raw.as_deref(),
+                                    fqn.as_deref(),
+                                    jmes_path.as_deref(),
+                                    Some(value.evaluated),
+                                )?;
+                            }
+                            RESTORE_UPDATE
+                        }
+                    }
+                };
+                let display = match (&fqn, param.secret && !show_secrets) {
+                    (Some(fqn), _) => fqn.clone(),
+                    (None, true) => REDACTED.to_string(),
+                    (None, false) => raw.clone().unwrap_or_default(),
+                };
+                changes.push(RestoreDetails {
+                    object_type: "value".to_string(),
+                    name: key.clone(),
+                    value: display,
+                    action: action.to_string(),
+                    project: proj_name.clone(),
+                    environment: env_name.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Restores the templates of the projects in the specified `order`.
+///
+/// This is done after all the parameters, since templates may reference parameters in any project.
+fn restore_templates(
+    rest_cfg: &OpenApiConfig,
+    snapshot: &BackupSnapshotDetails,
+    order: &[String],
+    proj_ids: &HashMap<String, String>,
+    preview: bool,
+    changes: &mut Vec<RestoreDetails>,
+) -> Result<()> {
+    let templates = Templates::new();
+    for proj_name in order {
+        let project = snapshot
+            .projects
+            .values()
+            .find(|p| &p.name == proj_name)
+            .unwrap();
+        let proj_id = proj_ids.get(proj_name);
+        let mut existing: HashMap<String, TemplateDetails> = HashMap::new();
+        if let Some(proj_id) = proj_id {
+            for details in templates.get_template_details(rest_cfg, proj_id)? {
+                existing.insert(details.name.clone(), details);
+            }
+        }
+
+        let mut template_list: Vec<_> = project.templates.values().collect();
+        template_list.sort_by(|l, r| l.name.cmp(&r.name));
+        for template in template_list {
+            let name = &template.name;
+            let description = template.description.clone().unwrap_or_default();
+            let action = if let Some(details) = existing.get(name) {
+                if details.body == template.text && details.description == description {
+                    RESTORE_UNCHANGED
+                } else {
+                    if !preview {
+                        templates.update_template(
+                            rest_cfg,
+                            proj_id.unwrap(),
+                            &details.id,
+                            name,
+                            Some(&description),
+                            Some(&template.text),
+                        )?;
+                    }
+                    RESTORE_UPDATE
+                }
+            } else {
+                if let (false, Some(proj_id)) = (preview, proj_id) {
+                    templates.create_template(
+                        rest_cfg,
+                        proj_id,
+                        name,
+                        &template.text,
+                        template.description.as_deref(),
+                    )?;
+                }
+                RESTORE_CREATE
+            };
+            changes.push(RestoreDetails {
+                object_type: "template".to_string(),
+                name: name.clone(),
+                action: action.to_string(),
+                project: proj_name.clone(),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(())
+}
+
+fn proc_back_restore(subcmd_args: &ArgMatches, rest_cfg: &OpenApiConfig) -> Result<()> {
+    let filename = subcmd_args.value_of("file").unwrap();
+    let preview = subcmd_args.is_present("preview");
+    let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let key_file = subcmd_args.value_of(KEY_FILE_OPT);
+    let text = read_backup_file(filename, key_file)?;
+    let snapshot: BackupSnapshotDetails = deserialize_text(&text, filename, "snapshot file");
+
+    let mut confirmed = preview || subcmd_args.is_present(CONFIRM_FLAG);
+    if !confirmed {
+        confirmed = user_confirm(
+            format!(
+                "Restore snapshot '{filename}' (taken {})",
+                snapshot.timestamp
+            ),
+            Some(false),
+        );
+    }
+    if !confirmed {
+        warning_message("No restore done".to_string());
+        return Ok(());
+    }
+
+    let mut changes: Vec<RestoreDetails> = vec![];
+    let env_ids = restore_environments(rest_cfg, &snapshot, preview, &mut changes)?;
+    restore_types(rest_cfg, &snapshot, preview, &mut changes)?;
+    let (order, proj_ids) = restore_projects(rest_cfg, &snapshot, preview, &mut changes)?;
+    restore_parameters(
+        rest_cfg,
+        &snapshot,
+        &order,
+        &proj_ids,
+        &env_ids,
+        preview,
+        show_secrets,
+        &mut changes,
+    )?;
+    restore_templates(
+        rest_cfg,
+        &snapshot,
+        &order,
+        &proj_ids,
+        preview,
+        &mut changes,
+    )?;
+
+    if changes.is_empty() {
+        println!("Nothing to restore.");
+    } else {
+        let hdr = vec!["Type", "Name", "Value", "Change", "Project", "Environment"];
+        let properties = vec!["type", "name", "value", "action", "project", "environment"];
+        let mut table = Table::new("restore");
+        table.set_header(&hdr);
+        for entry in changes {
+            table.add_row(entry.get_properties(&properties));
+        }
+        table.render(fmt)?;
+    }
+    Ok(())
+}
+
+/// A property of a snapshot object that is compared by `backup diff`.
+struct DiffProperty {
+    name: &'static str,
+    value: String,
+    secret: bool,
+}
+
+impl DiffProperty {
+    fn new(name: &'static str, value: String) -> Self {
+        Self {
+            name,
+            value,
+            secret: false,
+        }
+    }
+
+    fn display(&self, show_secrets: bool) -> String {
+        match self.secret && !show_secrets && !self.value.is_empty() {
+            true => REDACTED.to_string(),
+            false => self.value.clone(),
+        }
+    }
+}
+
+/// Summarizes the non-empty, single-line properties of an added or removed object.
+fn property_summary(properties: &[DiffProperty], show_secrets: bool) -> String {
+    properties
+        .iter()
+        .filter(|p| !p.value.is_empty() && !p.value.contains('\n'))
+        .map(|p| format!("{}: {}", p.name, p.display(show_secrets)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Describes the properties that differ between the `old` and `new` versions of an object.
+///
+/// Multi-line values (e.g. template bodies) are shown as a unified diff.
+fn property_differences(
+    old: &[DiffProperty],
+    new: &[DiffProperty],
+    show_secrets: bool,
+    context: usize,
+) -> String {
+    let mut lines: Vec<String> = vec![];
+    for (before, after) in old.iter().zip(new.iter()) {
+        if before.value == after.value {
+            continue;
+        }
+        let secret = (before.secret || after.secret) && !show_secrets;
+        if !secret && (before.value.contains('\n') || after.value.contains('\n')) {
+            let diff = TextDiff::from_lines(&before.value, &after.value);
+            let unified = diff.unified_diff().context_radius(context).to_string();
+            lines.push(format!("{}:\n{}", after.name, unified.trim_end()));
+        } else {
+            lines.push(format!(
+                "{}: {} -> {}",
+                after.name,
+                before.display(show_secrets),
+                after.display(show_secrets)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Compares the `old` and `new` objects (keyed by name), and returns the `(name, change, details)`
+/// of each one that differs.
+fn diff_objects<T>(
+    old: &BTreeMap<String, &T>,
+    new: &BTreeMap<String, &T>,
+    properties: impl Fn(&T) -> Vec<DiffProperty>,
+    show_secrets: bool,
+    context: usize,
+) -> Vec<(String, &'static str, String)> {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut result = vec![];
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (None, Some(item)) => result.push((
+                name.clone(),
+                DIFF_ADDED,
+                property_summary(&properties(item), show_secrets),
+            )),
+            (Some(item), None) => result.push((
+                name.clone(),
+                DIFF_REMOVED,
+                property_summary(&properties(item), show_secrets),
+            )),
+            (Some(before), Some(after)) => {
+                let details = property_differences(
+                    &properties(before),
+                    &properties(after),
+                    show_secrets,
+                    context,
+                );
+                if !details.is_empty() {
+                    result.push((name.clone(), DIFF_CHANGED, details));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    result
+}
+
+fn rule_properties(rule: &BackupParameterRule) -> Vec<DiffProperty> {
+    vec![DiffProperty::new("constraint", rule.constraint.clone())]
+}
+
+fn rule_map(
+    rules: Option<&HashMap<String, BackupParameterRule>>,
+) -> BTreeMap<String, &BackupParameterRule> {
+    rules
+        .map(|r| r.values().map(|r| (r.rule_type.clone(), r)).collect())
+        .unwrap_or_default()
+}
+
+/// Parameters that belong to the project (rather than being inherited from a parent).
+fn own_parameters(project: Option<&BackupProject>) -> BTreeMap<String, &BackupParameter> {
+    project
+        .map(|p| {
+            p.parameters
+                .values()
+                .filter(|param| param.project == p.name)
+                .map(|param| (param.name.clone(), param))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Values that are set in the environment (rather than being inherited from a parent).
+fn own_values(param: Option<&BackupParameter>) -> BTreeMap<String, &BackupParameterValue> {
+    param
+        .map(|p| {
+            p.values
+                .values()
+                .filter(|v| v.source.is_none() || v.source.as_ref() == Some(&v.environment))
+                .map(|v| (v.environment.clone(), v))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compares two snapshots, and returns a row for each difference.
+fn snapshot_differences(
+    old: &BackupSnapshotDetails,
+    new: &BackupSnapshotDetails,
+    show_secrets: bool,
+    context: usize,
+) -> Vec<RestoreDetails> {
+    let mut changes: Vec<RestoreDetails> = vec![];
+    let mut add_rows = |object_type: &str,
+                        rows: Vec<(String, &'static str, String)>,
+                        name: Option<&str>,
+                        project: &str| {
+        for (key, action, details) in rows {
+            // for rules and values, the key is the rule type or environment name
+            let (name, value, environment) = match (name, object_type) {
+                (Some(n), "value") => (n.to_string(), details, key),
+                (Some(n), _) => (n.to_string(), format!("{key} {details}"), String::new()),
+                (None, _) => (key, details, String::new()),
+            };
+            changes.push(RestoreDetails {
+                object_type: object_type.to_string(),
+                name,
+                value: value.trim_end().to_string(),
+                action: action.to_string(),
+                project: project.to_string(),
+                environment,
+            });
+        }
+    };
+
+    let old_envs: BTreeMap<String, _> = old
+        .environments
+        .values()
+        .map(|e| (e.name.clone(), e))
+        .collect();
+    let new_envs: BTreeMap<String, _> = new
+        .environments
+        .values()
+        .map(|e| (e.name.clone(), e))
+        .collect();
+    let env_props = |e: &BackupEnvironment| {
+        vec![
+            DiffProperty::new("description", e.description.clone().unwrap_or_default()),
+            DiffProperty::new("parent", e.parent.clone().unwrap_or_default()),
+        ]
+    };
+    add_rows(
+        "environment",
+        diff_objects(&old_envs, &new_envs, env_props, show_secrets, context),
+        None,
+        "",
+    );
+
+    let old_types: BTreeMap<String, _> = old.types.values().map(|t| (t.name.clone(), t)).collect();
+    let new_types: BTreeMap<String, _> = new.types.values().map(|t| (t.name.clone(), t)).collect();
+    let type_props = |t: &BackupParameterType| {
+        vec![
+            DiffProperty::new("description", t.description.clone().unwrap_or_default()),
+            DiffProperty::new("parent", t.parent.clone()),
+        ]
+    };
+    add_rows(
+        "type",
+        diff_objects(&old_types, &new_types, type_props, show_secrets, context),
+        None,
+        "",
+    );
+    let type_names: BTreeSet<&String> = old_types.keys().chain(new_types.keys()).collect();
+    for name in type_names {
+        let old_rules = rule_map(old_types.get(name).map(|t| &t.rules));
+        let new_rules = rule_map(new_types.get(name).map(|t| &t.rules));
+        add_rows(
+            "type-rule",
+            diff_objects(
+                &old_rules,
+                &new_rules,
+                rule_properties,
+                show_secrets,
+                context,
+            ),
+            Some(name),
+            "",
+        );
+    }
+
+    let old_projs: BTreeMap<String, _> =
+        old.projects.values().map(|p| (p.name.clone(), p)).collect();
+    let new_projs: BTreeMap<String, _> =
+        new.projects.values().map(|p| (p.name.clone(), p)).collect();
+    let proj_props = |p: &BackupProject| {
+        vec![
+            DiffProperty::new("description", p.description.clone().unwrap_or_default()),
+            DiffProperty::new("parent", p.parent.clone().unwrap_or_default()),
+        ]
+    };
+    add_rows(
+        "project",
+        diff_objects(&old_projs, &new_projs, proj_props, show_secrets, context),
+        None,
+        "",
+    );
+
+    let proj_names: BTreeSet<&String> = old_projs.keys().chain(new_projs.keys()).collect();
+    for proj_name in proj_names {
+        let old_proj = old_projs.get(proj_name).copied();
+        let new_proj = new_projs.get(proj_name).copied();
+        let old_params = own_parameters(old_proj);
+        let new_params = own_parameters(new_proj);
+        let param_props = |p: &BackupParameter| {
+            vec![
+                DiffProperty::new("description", p.description.clone().unwrap_or_default()),
+                DiffProperty::new("type", p.param_type.clone()),
+                DiffProperty::new("secret", p.secret.to_string()),
+            ]
+        };
+        add_rows(
+            "parameter",
+            diff_objects(&old_params, &new_params, param_props, show_secrets, context),
+            None,
+            proj_name,
+        );
+
+        let param_names: BTreeSet<&String> = old_params.keys().chain(new_params.keys()).collect();
+        for key in param_names {
+            let old_param = old_params.get(key).copied();
+            let new_param = new_params.get(key).copied();
+            let old_rules = rule_map(old_param.map(|p| &p.rules));
+            let new_rules = rule_map(new_param.map(|p| &p.rules));
+            add_rows(
+                "parameter-rule",
+                diff_objects(
+                    &old_rules,
+                    &new_rules,
+                    rule_properties,
+                    show_secrets,
+                    context,
+                ),
+                Some(key),
+                proj_name,
+            );
+
+            // a value is treated as secret when the parameter is secret in either snapshot
+            let secret = old_param.map(|p| p.secret).unwrap_or_default()
+                || new_param.map(|p| p.secret).unwrap_or_default();
+            let value_props = |v: &BackupParameterValue| {
+                let external = v.external.as_ref();
+                let value = match external {
+                    Some(_) => String::new(),
+                    None => v
+                        .raw
+                        .clone()
+                        .or_else(|| v.value.clone())
+                        .unwrap_or_default(),
+                };
+                vec![
+                    DiffProperty {
+                        name: "value",
+                        value,
+                        secret,
+                    },
+                    DiffProperty::new("fqn", external.map(|e| e.fqn.clone()).unwrap_or_default()),
+                    DiffProperty::new(
+                        "jmes-path",
+                        external
+                            .and_then(|e| e.jmes_path.clone())
+                            .unwrap_or_default(),
+                    ),
+                    DiffProperty::new("evaluated", v.evaluated.to_string()),
+                ]
+            };
+            add_rows(
+                "value",
+                diff_objects(
+                    &own_values(old_param),
+                    &own_values(new_param),
+                    value_props,
+                    show_secrets,
+                    context,
+                ),
+                Some(key),
+                proj_name,
+            );
+        }
+
+        let old_temps: BTreeMap<String, _> = old_proj
+            .map(|p| p.templates.values().map(|t| (t.name.clone(), t)).collect())
+            .unwrap_or_default();
+        let new_temps: BTreeMap<String, _> = new_proj
+            .map(|p| p.templates.values().map(|t| (t.name.clone(), t)).collect())
+            .unwrap_or_default();
+        let temp_props = |t: &BackupTemplate| {
+            vec![
+                DiffProperty::new("description", t.description.clone().unwrap_or_default()),
+                DiffProperty::new("text", t.text.clone()),
+            ]
+        };
+        add_rows(
+            "template",
+            diff_objects(&old_temps, &new_temps, temp_props, show_secrets, context),
+            None,
+            proj_name,
+        );
+    }
+    changes
+}
+
+fn proc_back_diff(subcmd_args: &ArgMatches) -> Result<()> {
+    let old_file = subcmd_args.value_of("old").unwrap();
+    let new_file = subcmd_args.value_of("new").unwrap();
+    let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let key_file = subcmd_args.value_of(KEY_FILE_OPT);
+    let lines = subcmd_args.value_of("lines").unwrap();
+    let context = match lines.parse::<usize>() {
+        Ok(context) => context,
+        Err(_) => {
+            error_message(format!(
+                "Invalid --context value '{lines}', expected a number of lines"
+            ));
+            process::exit(80);
+        }
+    };
+    let text = read_backup_file(old_file, key_file)?;
+    let old: BackupSnapshotDetails = deserialize_text(&text, old_file, "snapshot file");
+    let text = read_backup_file(new_file, key_file)?;
+    let new: BackupSnapshotDetails = deserialize_text(&text, new_file, "snapshot file");
+
+    let changes = snapshot_differences(&old, &new, show_secrets, context);
+    if changes.is_empty() {
+        println!("No differences found between '{old_file}' and '{new_file}'.");
+    } else {
+        let hdr = vec![
+            "Type",
+            "Name",
+            "Change",
+            "Details",
+            "Project",
+            "Environment",
+        ];
+        let properties = vec!["type", "name", "action", "value", "project", "environment"];
+        let mut table = Table::new("difference");
+        table.set_header(&hdr);
+        for entry in changes {
+            table.add_row(entry.get_properties(&properties));
+        }
+        table.render(fmt)?;
+    }
+    Ok(())
+}
+
+/// Serializes the `item` into the specified format.
+fn serialize<T: Serialize>(item: &T, fmt: &str) -> String {
+    match fmt {
+        "yaml" => serde_yaml::to_string(item).unwrap(),
+        "json" => serde_json::to_string_pretty(item).unwrap(),
+        _ => {
+            error_message(format!("Unsupported format {fmt}"));
+            process::exit(55);
+        }
+    }
+}
+
+/// Parses YAML or JSON text (YAML is a superset of JSON) into the desired type.
+fn deserialize_text<T: DeserializeOwned>(text: &str, filename: &str, description: &str) -> T {
+    match serde_yaml::from_str(text) {
+        Ok(item) => item,
+        Err(e) => {
+            error_message(format!("Failed to parse {description} '{filename}': {e}"));
+            process::exit(56);
+        }
+    }
+}
+
+/// Gets the key material from the `key_file`, or prompts the user for a passphrase.
+///
+/// The prompts go to stderr, so they do not get mixed into the backup output.
+fn key_material(key_file: Option<&str>, confirm: bool) -> Result<Vec<u8>> {
+    if let Some(key_file) = key_file {
+        return Ok(fs::read(key_file).expect(FILE_READ_ERR));
+    }
+    eprintln!("Please enter the backup passphrase: ");
+    let passphrase = read_password()?;
+    if passphrase.is_empty() {
+        error_message("A non-empty passphrase is required");
+        process::exit(58);
+    }
+    if confirm {
+        eprintln!("Please re-enter the backup passphrase: ");
+        if read_password()? != passphrase {
+            error_message("The passphrases do not match");
+            process::exit(58);
+        }
+    }
+    Ok(passphrase.into_bytes())
+}
+
+/// Wraps the `text` in an encrypted envelope, when encryption is enabled.
+fn protect(text: String, fmt: &str, encryption: &Encryption) -> Result<String> {
+    match encryption {
+        Some((algorithm, kdf, key)) => {
+            let envelope = CryptoEnvelope::encrypt(algorithm, kdf, key, text.as_bytes())?;
+            Ok(serialize(&envelope, fmt))
+        }
+        None => Ok(text),
+    }
+}
+
+/// Reads a backup file, and decrypts it when it holds an encrypted envelope.
+fn read_backup_file(filename: &str, key_file: Option<&str>) -> Result<String> {
+    let text = fs::read_to_string(filename).expect(FILE_READ_ERR);
+    match serde_yaml::from_str::<CryptoEnvelope>(&text) {
+        Ok(envelope) => {
+            let key = key_material(key_file, false)?;
+            let plaintext = envelope.decrypt(&key)?;
+            Ok(String::from_utf8(plaintext)?)
+        }
+        Err(_) => Ok(text),
+    }
+}
+
+/// Converts the name into something that is safe to use as a filename.
+fn file_safe_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "-_.".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// The snapshot file for the project. Different names may have the same file-safe name (e.g.
+/// 'a b' and 'a_b'), so a digest of the real name keeps them apart.
+fn project_file_name(name: &str, fmt: &str) -> String {
+    let digest = hex::encode(Sha256::digest(name.as_bytes()));
+    format!("project-{}-{}.{fmt}", file_safe_name(name), &digest[..8])
+}
+
+/// Removes projects, environments (and their values), and types that were not requested.
+fn filter_snapshot(
+    snapshot: &mut BackupSnapshotDetails,
+    proj_names: &[String],
+    env_names: &[String],
+    type_names: &[String],
+) {
+    let existing: Vec<&String> = snapshot.projects.values().map(|p| &p.name).collect();
+    for name in proj_names.iter().filter(|n| !existing.contains(n)) {
+        warning_message(format!("Project '{name}' not found in snapshot"));
+    }
+    let existing: Vec<&String> = snapshot.environments.values().map(|e| &e.name).collect();
+    for name in env_names.iter().filter(|n| !existing.contains(n)) {
+        warning_message(format!("Environment '{name}' not found in snapshot"));
+    }
+    let existing: Vec<&String> = snapshot.types.values().map(|t| &t.name).collect();
+    for name in type_names.iter().filter(|n| !existing.contains(n)) {
+        warning_message(format!("Parameter type '{name}' not found in snapshot"));
+    }
+
+    if !proj_names.is_empty() {
+        snapshot
+            .projects
+            .retain(|_, p| proj_names.contains(&p.name));
+    }
+    if !env_names.is_empty() {
+        snapshot
+            .environments
+            .retain(|_, e| env_names.contains(&e.name));
+        for project in snapshot.projects.values_mut() {
+            for param in project.parameters.values_mut() {
+                param
+                    .values
+                    .retain(|_, v| env_names.contains(&v.environment));
+            }
+        }
+    }
+    if !type_names.is_empty() {
+        snapshot.types.retain(|_, t| type_names.contains(&t.name));
+    }
+}
+
+/// The path of the `file` relative to the `dir`, so a manifest can refer to the files of a
+/// snapshot in another directory.
+fn relative_path(dir: &Path, file: &Path) -> Result<String> {
+    let dir = fs::canonicalize(dir)?;
+    let file = fs::canonicalize(file)?;
+    let common = dir
+        .components()
+        .zip(file.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        // nothing in common (e.g. another drive), so only the full path works
+        return Ok(file.display().to_string());
+    }
+    let mut result = PathBuf::new();
+    for _ in common..dir.components().count() {
+        result.push("..");
+    }
+    result.extend(file.components().skip(common));
+    Ok(result.display().to_string())
+}
+
+/// Writes the snapshot as a set of files in `dir`: one per project, one with the environments and
+/// parameter types, and the manifest.
+///
+/// The files may hold secret values, so only the current user can read them. With a previous
+/// manifest (and its directory), the projects that have not changed are not written again, and
+/// the manifest refers to the previous files instead. Every file (other than the manifest) is a
+/// snapshot that can be used with `backup restore`.
+fn write_snapshot_files(
+    dir: &str,
+    fmt: &str,
+    snapshot: &BackupSnapshotDetails,
+    since: Option<(&Path, &BackupManifest)>,
+    encryption: &Encryption,
+) -> Result<()> {
+    let dir_path = Path::new(dir);
+    fs::create_dir_all(dir_path)?;
+
+    // the manifest has digests for everything, so it can be the baseline for the next one
+    let mut manifest = BackupManifest::from(snapshot);
+    if let Some((_, previous)) = since {
+        manifest.since = Some(previous.timestamp.clone());
+    }
+
+    // the environments and types are always written, so the shared file is never partial
+    if !snapshot.environments.is_empty() || !snapshot.types.is_empty() {
+        let filename = format!("shared.{fmt}");
+        let shared = BackupSnapshotDetails {
+            environments: snapshot.environments.clone(),
+            types: snapshot.types.clone(),
+            timestamp: snapshot.timestamp.clone(),
+            ..Default::default()
+        };
+        let text = protect(serialize(&shared, fmt), fmt, encryption)?;
+        write_private(&dir_path.join(&filename), &text, true)?;
+        println!("Wrote {}", dir_path.join(&filename).display());
+        manifest.shared = Some(filename);
+    }
+
+    for (key, project) in &snapshot.projects {
+        // unchanged projects still live in the files written by a previous snapshot
+        let reused = since
+            .and_then(|(since_dir, previous)| {
+                previous
+                    .unchanged_file(project)
+                    .map(|file| since_dir.join(file))
+            })
+            .filter(|path| path.is_file());
+        let filename = match reused {
+            Some(path) => relative_path(dir_path, &path)?,
+            None => {
+                let filename = project_file_name(&project.name, fmt);
+                let single = BackupSnapshotDetails {
+                    projects: HashMap::from([(key.clone(), project.clone())]),
+                    timestamp: snapshot.timestamp.clone(),
+                    ..Default::default()
+                };
+                let text = protect(serialize(&single, fmt), fmt, encryption)?;
+                write_private(&dir_path.join(&filename), &text, true)?;
+                println!("Wrote {}", dir_path.join(&filename).display());
+                filename
+            }
+        };
+        if let Some(entry) = manifest.projects.get_mut(&project.name) {
+            entry.file = Some(filename);
+        }
+    }
+
+    // the manifest only has names and digests, so it is never encrypted
+    let filename = format!("manifest.{fmt}");
+    write_private(&dir_path.join(&filename), &serialize(&manifest, fmt), true)?;
+    println!("Wrote {}", dir_path.join(&filename).display());
+    Ok(())
+}
 
 fn proc_back_snapshot(
     subcmd_args: &ArgMatches,
@@ -12,41 +1403,270 @@ fn proc_back_snapshot(
     backups: &Backups,
 ) -> Result<()> {
     let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let proj_names = subcmd_args.values_of_lossy("project").unwrap_or_default();
+    let env_names = subcmd_args
+        .values_of_lossy("environment")
+        .unwrap_or_default();
+    let type_names = subcmd_args.values_of_lossy("type").unwrap_or_default();
+    let output = subcmd_args.value_of("output");
+    let since_file = subcmd_args.value_of("since");
+    let previous: Option<BackupManifest> = since_file.map(|f| {
+        let text = fs::read_to_string(f).expect(FILE_READ_ERR);
+        deserialize_text(&text, f, "snapshot manifest")
+    });
+    let encrypt = subcmd_args.is_present("encrypt");
     let mut confirmed = subcmd_args.is_present(CONFIRM_FLAG);
     if !confirmed {
-        printdoc!(
-            r#"
-            This action will include unprotected secret values (if any) in output. The resulting
-            data needs to be protected appropriately.
-            "#
-        );
-        confirmed = user_confirm("Backup everything to console".to_string(), Some(true));
+        if !encrypt {
+            printdoc!(
+                r#"
+                This action will include unprotected secret values (if any) in output. The resulting
+                data needs to be protected appropriately.
+                "#
+            );
+        }
+        let msg = match output {
+            Some(dir) => format!("Backup to directory '{dir}'"),
+            None => "Backup to console".to_string(),
+        };
+        confirmed = user_confirm(msg, Some(true));
     }
 
     if !confirmed {
         warning_message("No backup done".to_string());
     } else {
-        let snapshot = backups.data_snapshot(rest_cfg)?;
-        match fmt {
-            "yaml" => println!("{}", serde_yaml::to_string(&snapshot).unwrap()),
-            "json" => println!("{}", serde_json::to_string_pretty(&snapshot).unwrap()),
-            _ => {
-                error_message(format!("Unsupported format {fmt}"));
-                process::exit(55);
+        let encryption: Encryption = match encrypt {
+            true => {
+                let algorithm = subcmd_args.value_of("algorithm").unwrap_or("aes_gcm");
+                let key_file = subcmd_args.value_of(KEY_FILE_OPT);
+                // key files hold random bytes, but passphrases need a slow KDF to resist guessing
+                let kdf = match key_file {
+                    Some(_) => CryptoKdf::HkdfSha256,
+                    None => CryptoKdf::passphrase(),
+                };
+                Some((
+                    CryptoAlgorithm::from_str(algorithm).unwrap(),
+                    kdf,
+                    key_material(key_file, true)?,
+                ))
             }
+            false => None,
+        };
+        let mut snapshot = backups.data_snapshot(rest_cfg)?;
+        filter_snapshot(&mut snapshot, &proj_names, &env_names, &type_names);
+
+        if let Some(dir) = output {
+            // the files in the previous manifest are relative to its directory
+            let since = since_file
+                .and_then(|f| Path::new(f).parent())
+                .zip(previous.as_ref());
+            write_snapshot_files(dir, fmt, &snapshot, since, &encryption)?;
+        } else {
+            println!("{}", protect(serialize(&snapshot, fmt), fmt, &encryption)?);
         }
     }
 
     Ok(())
 }
 
+fn proc_back_decrypt(subcmd_args: &ArgMatches) -> Result<()> {
+    let filename = subcmd_args.value_of("file").unwrap();
+    let key_file = subcmd_args.value_of(KEY_FILE_OPT);
+    println!("{}", read_backup_file(filename, key_file)?);
+    Ok(())
+}
+
 /// Process the 'backup' sub-command
 pub fn process_backup_command(subcmd_args: &ArgMatches, rest_cfg: &OpenApiConfig) -> Result<()> {
     let backups = Backups::new();
     if let Some(subcmd_args) = subcmd_args.subcommand_matches("snapshot") {
         proc_back_snapshot(subcmd_args, rest_cfg, &backups)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("restore") {
+        proc_back_restore(subcmd_args, rest_cfg)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(DIFF_SUBCMD) {
+        proc_back_diff(subcmd_args)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("decrypt") {
+        proc_back_decrypt(subcmd_args)?;
     } else {
         warn_missing_subcommand("backup");
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, parent: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), parent.map(String::from))
+    }
+
+    #[test]
+    fn dependency_order_parents_first() {
+        let items = vec![
+            item("alpha", Some("zulu")),
+            item("bravo", None),
+            item("charlie", Some("alpha")),
+            item("zulu", Some("default")),
+        ];
+        let order = dependency_order(&items);
+        assert_eq!(order, vec!["bravo", "zulu", "alpha", "charlie"]);
+    }
+
+    #[test]
+    fn dependency_order_circular() {
+        let items = vec![item("a", Some("b")), item("b", Some("a")), item("c", None)];
+        let order = dependency_order(&items);
+        assert_eq!(order, vec!["c", "a", "b"]);
+    }
+
+    fn one_value_snapshot(value: &str, secret: bool) -> BackupSnapshotDetails {
+        let text = format!(
+            r#"
+            timestamp: now
+            environments:
+              default: {{name: default}}
+            types: {{}}
+            projects:
+              proj:
+                name: proj
+                templates: {{}}
+                parameters:
+                  key:
+                    name: key
+                    project: proj
+                    param_type: string
+                    secret: {secret}
+                    rules: {{}}
+                    values:
+                      default: {{environment: default, evaluated: false, value: {value}}}
+            "#
+        );
+        serde_yaml::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn snapshot_differences_redacts_secrets() {
+        let old = one_value_snapshot("abc", true);
+        let new = one_value_snapshot("def", true);
+        assert!(snapshot_differences(&old, &old, false, 3).is_empty());
+
+        let changes = snapshot_differences(&old, &new, false, 3);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].object_type, "value");
+        assert_eq!(changes[0].action, DIFF_CHANGED);
+        assert_eq!(changes[0].environment, "default");
+        assert_eq!(changes[0].value, format!("value: {REDACTED} -> {REDACTED}"));
+
+        let changes = snapshot_differences(&old, &new, true, 3);
+        assert_eq!(changes[0].value, "value: abc -> def");
+    }
+
+    #[test]
+    fn snapshot_differences_added_removed() {
+        let old = one_value_snapshot("abc", false);
+        let new = BackupSnapshotDetails {
+            timestamp: "later".to_string(),
+            ..Default::default()
+        };
+        let changes = snapshot_differences(&old, &new, false, 3);
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|c| (c.object_type.as_str(), c.action.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("environment", DIFF_REMOVED),
+                ("project", DIFF_REMOVED),
+                ("parameter", DIFF_REMOVED),
+                ("value", DIFF_REMOVED),
+            ]
+        );
+    }
+
+    fn two_env_snapshot(description: &str) -> BackupSnapshotDetails {
+        let text = format!(
+            r#"
+            timestamp: {description}
+            environments:
+              default: {{name: default}}
+              production: {{name: production, description: {description}}}
+            types: {{}}
+            projects:
+              proj:
+                name: proj
+                templates: {{}}
+                parameters: {{}}
+            "#
+        );
+        serde_yaml::from_str(&text).unwrap()
+    }
+
+    fn read_snapshot(path: &Path) -> BackupSnapshotDetails {
+        let text = read_backup_file(path.to_str().unwrap(), None).unwrap();
+        serde_yaml::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn incremental_snapshot_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first_dir = tmp.path().join("first");
+        let first = first_dir.to_str().unwrap();
+        write_snapshot_files(first, "yaml", &two_env_snapshot("old"), None, &None).unwrap();
+        let manifest_text = fs::read_to_string(first_dir.join("manifest.yaml")).unwrap();
+        let previous: BackupManifest = serde_yaml::from_str(&manifest_text).unwrap();
+
+        // only the production environment changed, in a new directory and in the same one
+        let changed = two_env_snapshot("new");
+        let second_dir = tmp.path().join("second");
+        for dir in [&second_dir, &first_dir] {
+            let since = Some((first_dir.as_path(), &previous));
+            write_snapshot_files(dir.to_str().unwrap(), "yaml", &changed, since, &None).unwrap();
+            let manifest_text = fs::read_to_string(dir.join("manifest.yaml")).unwrap();
+            let manifest: BackupManifest = serde_yaml::from_str(&manifest_text).unwrap();
+            assert_eq!(manifest.since.as_deref(), Some("old"));
+
+            // the unchanged environment can still be restored from the shared file
+            let shared = read_snapshot(&dir.join(manifest.shared.unwrap()));
+            let mut env_names: Vec<&str> = shared
+                .environments
+                .values()
+                .map(|e| e.name.as_str())
+                .collect();
+            env_names.sort();
+            assert_eq!(env_names, vec!["default", "production"]);
+
+            // the unchanged project refers to the file from the first snapshot
+            let file = manifest.projects["proj"].file.clone().unwrap();
+            if dir == &second_dir {
+                assert_eq!(
+                    Path::new(&file),
+                    Path::new("..")
+                        .join("first")
+                        .join(project_file_name("proj", "yaml"))
+                );
+            }
+            let project = read_snapshot(&dir.join(file));
+            assert_eq!(project.timestamp, "old");
+            assert!(project.projects.contains_key("proj"));
+        }
+        assert!(!second_dir.join(project_file_name("proj", "yaml")).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let file = first_dir.join(project_file_name("proj", "yaml"));
+            let mode = fs::metadata(file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn project_file_names_are_unique() {
+        let names = ["a b", "a/b", "a_b", "A_B"];
+        let files: HashSet<String> = names.iter().map(|n| project_file_name(n, "yaml")).collect();
+        assert_eq!(files.len(), names.len());
+        assert!(project_file_name("a/b", "yaml").starts_with("project-a_b-"));
+        assert!(project_file_name("a/b", "yaml").ends_with(".yaml"));
+    }
+}
@@ -20,6 +20,11 @@ pub const INTEGRATION_NAME_ARG: &str = "integration-name";
 pub const INVITE_NAME_ARG: &str = "e-mail";
 pub const JMES_PATH_ARG: &str = "JMES";
 pub const KEY_ARG: &str = "KEY";
+pub const KEY_FILE_OPT: &str = "key-file";
+pub const KEY_PREFIX_OPT: &str = "prefix";
+pub const KEY_SANITIZE_FLAG: &str = "sanitize";
+pub const KEY_STRIP_PREFIX_OPT: &str = "strip-prefix";
+pub const KEY_UPPERCASE_FLAG: &str = "uppercase";
 pub const NAME_ARG: &str = "NAME";
 pub const PARENT_ARG: &str = "parent";
 pub const PROJECT_NAME_OPT: &str = "project";
@@ -289,6 +294,64 @@ fn role_arg() -> Arg<'static, 'static> {
         .possible_values(&["owner", "admin", "contrib", "viewer"])
 }
 
+fn grant_principal_args(required: bool) -> [Arg<'static, 'static>; 2] {
+    [
+        Arg::with_name("user")
+            .short("u")
+            .long("user")
+            .takes_value(true)
+            .required_unless("group")
+            .conflicts_with("group")
+            .help("User name of the grant principal"),
+        Arg::with_name("group")
+            .short("g")
+            .long("group")
+            .takes_value(true)
+            .required(false)
+            .help("Group name of the grant principal"),
+    ]
+    .map(|arg| if required { arg } else { arg.required(false) })
+}
+
+fn grant_scope_args(required: bool) -> [Arg<'static, 'static>; 2] {
+    [
+        Arg::with_name("project")
+            .short("p")
+            .long("project")
+            .takes_value(true)
+            .required_unless("environment")
+            .conflicts_with("environment")
+            .help("Project name of the grant scope"),
+        Arg::with_name("environment")
+            .short("e")
+            .long("env")
+            .takes_value(true)
+            .help("Environment name of the grant scope"),
+    ]
+    .map(|arg| if required { arg } else { arg.required(false) })
+}
+
+fn key_transform_args() -> [Arg<'static, 'static>; 4] {
+    [
+        Arg::with_name(KEY_STRIP_PREFIX_OPT)
+            .long("strip-prefix")
+            .takes_value(true)
+            .value_name("PREFIX")
+            .help("Remove this prefix from the parameter names"),
+        Arg::with_name(KEY_PREFIX_OPT)
+            .long("prefix")
+            .takes_value(true)
+            .value_name("PREFIX")
+            .help("Add this prefix to the parameter names"),
+        Arg::with_name(KEY_UPPERCASE_FLAG)
+            .long("uppercase")
+            .help("Convert the parameter names to uppercase"),
+        Arg::with_name(KEY_SANITIZE_FLAG)
+            .long("sanitize")
+            .help("Replace characters that are not letters, digits or '_' in the parameter names with '_'"),
+    ]
+}
+
 fn invitation_name_arg() -> Arg<'static, 'static> {
     Arg::with_name(INVITE_NAME_ARG)
         .takes_value(true)
@@ -297,6 +360,14 @@ fn invitation_name_arg() -> Arg<'static, 'static> {
         .help("Email address for invitation")
 }
 
+fn integration_writable_arg() -> Arg<'static, 'static> {
+    Arg::with_name("writable")
+        .long("writable")
+        .takes_value(true)
+        .possible_values(TRUE_FALSE_VALUES)
+        .help("Allow CloudTruth to write to the integration")
+}
+
 fn integration_name_arg() -> Arg<'static, 'static> {
     Arg::with_name(INTEGRATION_NAME_ARG)
         .takes_value(true)
@@ -355,6 +426,13 @@ fn i32_validator(arg_value: String) -> Result<(), String> {
     }
 }
 
+fn key_file_opt() -> Arg<'static, 'static> {
+    Arg::with_name(KEY_FILE_OPT)
+        .long("key-file")
+        .value_name("file")
+        .takes_value(true)
+}
+
 fn serialized_format_arg() -> Arg<'static, 'static> {
     Arg::with_name(FORMAT_OPT)
         .takes_value(true)
@@ -810,6 +888,16 @@ pub fn build_cli() -> App<'static, 'static> {
                         .arg(jmes_path_arg())
                         .arg(raw_arg().help("Display raw file content (if only one file)"))
                         .arg(secrets_display_flag().help("Display raw values, even if secret")),
+                    SubCommand::with_name(DELETE_SUBCMD)
+                        .visible_aliases(DELETE_ALIASES)
+                        .about("Delete the specified integration")
+                        .arg(integration_name_arg())
+                        .arg(confirm_flag())
+                        .arg(Arg::with_name("in-use")
+                            .long("in-use")
+                            .takes_value(true)
+                            .possible_values(&["fail", "leave", "remove"])
+                            .help("Handling of values still using the integration (server default is leave)")),
                     SubCommand::with_name(GET_SUBCMD)
                         .about("Gets all the information for the specified integration")
                         .arg(integration_name_arg()),
@@ -823,6 +911,89 @@ pub fn build_cli() -> App<'static, 'static> {
                         .visible_aliases(&["ref", "re", "r"])
                         .about("Refresh the integration connection status")
                         .arg(integration_name_arg()),
+                    SubCommand::with_name("scan")
+                        .visible_aliases(&["sc"])
+                        .about("Show which external values an import resource pattern would match")
+                        .arg(integration_name_arg())
+                        .arg(Arg::with_name("resource")
+                            .long("resource")
+                            .takes_value(true)
+                            .default_value("/{{ environment }}/{{ project }}/{{ parameter }}")
+                            .help("Resource pattern to evaluate"))
+                        .arg(Arg::with_name("region")
+                            .long("region")
+                            .takes_value(true)
+                            .default_value("us-east-1")
+                            .possible_values(REGION_VALUES)
+                            .hide_possible_values(true) // list is too long, but want check
+                            .help("Region to scan (AWS only)"))
+                        .arg(Arg::with_name("service")
+                            .long("service")
+                            .takes_value(true)
+                            .default_value("ssm")
+                            .possible_values(&["ssm", "secretsmanager"])
+                            .help("Service to scan (AWS only)"))
+                        .arg(table_format_options().help("Format for scan results")),
+                    SubCommand::with_name(SET_SUBCMD)
+                        .visible_aliases(SET_ALIASES)
+                        .about("Create a CloudTruth integration")
+                        .subcommands(vec![
+                            SubCommand::with_name("aws")
+                                .about("Create an AWS integration")
+                                .arg(Arg::with_name("account-id")
+                                    .long("account-id")
+                                    .takes_value(true)
+                                    .required(true)
+                                    .help("AWS account identifier"))
+                                .arg(Arg::with_name("role-name")
+                                    .long("role-name")
+                                    .takes_value(true)
+                                    .required(true)
+                                    .help("AWS IAM role name CloudTruth assumes"))
+                                .arg(Arg::with_name("region")
+                                    .long("region")
+                                    .takes_value(true)
+                                    .multiple(true)
+                                    .number_of_values(1)
+                                    .required(true)
+                                    .possible_values(REGION_VALUES)
+                                    .hide_possible_values(true)
+                                    .help("Enabled AWS region -- may be specified multiple times"))
+                                .arg(Arg::with_name("service")
+                                    .long("service")
+                                    .takes_value(true)
+                                    .multiple(true)
+                                    .number_of_values(1)
+                                    .required(true)
+                                    .possible_values(&["s3", "secretsmanager", "ssm"])
+                                    .help("Enabled AWS service -- may be specified multiple times"))
+                                .arg(description_option().help("Integration description"))
+                                .arg(integration_writable_arg()),
+                            SubCommand::with_name("azure")
+                                .visible_aliases(&["azure-key-vault", "akv"])
+                                .about("Create an Azure Key Vault integration")
+                                .arg(Arg::with_name("tenant")
+                                    .long("tenant")
+                                    .takes_value(true)
+                                    .required(true)
+                                    .help("Azure tenant identifier"))
+                                .arg(Arg::with_name("vault")
+                                    .long("vault")
+                                    .takes_value(true)
+                                    .required(true)
+                                    .help("Azure Key Vault name"))
+                                .arg(description_option().help("Integration description"))
+                                .arg(integration_writable_arg()),
+                            SubCommand::with_name("github")
+                                .about("Create a GitHub integration")
+                                .arg(Arg::with_name("installation")
+                                    .long("installation")
+                                    .takes_value(true)
+                                    .required(true)
+                                    .help("GitHub application installation identifier"))
+                                .arg(description_option().help("Integration description"))
+                                .arg(integration_writable_arg()),
+                        ]),
                 ])
         )
         .subcommand(
@@ -854,7 +1025,11 @@ pub fn build_cli() -> App<'static, 'static> {
                         .visible_aliases(&["expo", "exp", "ex"])
                         .about(concat!("Export selected parameters to a known output format. ",
                             "Exported parameters are limited to alphanumeric and underscore  in ",
-                            "key names. Formats available are: dotenv, docker, and shell."))
+                            "key names. Formats available are: dotenv, docker, and shell, plus json, yaml, ",
+                            "nested-json and nested-yaml (where delimited key names become nested objects), ",
+                            "kubernetes (a ConfigMap, plus a Secret for the secret values), tfvars, ",
+                            "properties, hcl, github-actions (appended to $GITHUB_ENV, with secrets masked), ",
+                            "gitlab (dotenv artifact) and azure-pipelines (task.setvariable commands)."))
                         .arg(Arg::with_name("contains")
                             .long("contains")
                             .help("Return parameters with keys containing search")
@@ -871,9 +1046,50 @@ pub fn build_cli() -> App<'static, 'static> {
                             .possible_value("docker")
                             .possible_value("dotenv")
                             .possible_value("shell")
+                            .possible_value("json")
+                            .possible_value("yaml")
+                            .possible_value("nested-json")
+                            .possible_value("nested-yaml")
+                            .possible_value("kubernetes")
+                            .possible_value("tfvars")
+                            .possible_value("properties")
+                            .possible_value("hcl")
+                            .possible_value("github-actions")
+                            .possible_value("gitlab")
+                            .possible_value("azure-pipelines")
                             .index(1))
+                        .arg(Arg::with_name("name")
+                            .long("name")
+                            .takes_value(true)
+                            .required_if("FORMAT", "kubernetes")
+                            .help("Name of the kubernetes ConfigMap and Secret, or of the hcl map variable [default for hcl: cloudtruth]"))
+                        .arg(Arg::with_name("namespace")
+                            .long("namespace")
+                            .takes_value(true)
+                            .help("Namespace of the kubernetes ConfigMap and Secret"))
+                        .arg(Arg::with_name("list")
+                            .long("list")
+                            .help("Output the kubernetes objects as a single List, instead of separate documents"))
+                        .arg(Arg::with_name("delimiter")
+                            .long("delimiter")
+                            .takes_value(true)
+                            .help("Key delimiter for the nested-json and nested-yaml formats [default: .]"))
+                        .arg(Arg::with_name("locked")
+                            .long("locked")
+                            .takes_value(true)
+                            .value_name("FILE")
+                            .conflicts_with(AS_OF_ARG)
+                            .help("Export the values at the lock file tag/time, and refuse when they do not match the lock file"))
                         .arg(param_as_of_arg())
-                        .arg(secrets_display_flag().help("Display the secret parameter values"))
+                        .arg(secrets_display_flag()
+                            .required_ifs(&[
+                                ("FORMAT", "kubernetes"),
+                                ("FORMAT", "github-actions"),
+                                ("FORMAT", "gitlab"),
+                                ("FORMAT", "azure-pipelines"),
+                            ])
+                            .help("Display the secret parameter values (required for kubernetes and the CI formats)"))
+                        .args(&key_transform_args())
                         .arg(Arg::with_name("starts_with")
                             .long("starts-with")
                             .help("Return parameters starting with search")
@@ -979,6 +1195,13 @@ pub fn build_cli() -> App<'static, 'static> {
                         .arg(Arg::with_name("generate")
                             .long("generate")
                             .help("Generate a new value"))
+                        .arg(Arg::with_name("encrypt-with")
+                            .long("encrypt-with")
+                            .value_name("keyfile")
+                            .takes_value(true)
+                            .requires("secret")
+                            .conflicts_with_all(&["FQN", JMES_PATH_ARG])
+                            .help("Encrypt the secret value locally using the key material from this file"))
                         .arg(Arg::with_name("value")
                             .short("v")
                             .long("value")
@@ -1017,6 +1240,44 @@ pub fn build_cli() -> App<'static, 'static> {
                         .arg(table_format_options().help("Display difference format"))
                         .arg(secrets_display_flag().help("Show secret values"))
                         .arg(immediate_parameters_flag()),
+                    SubCommand::with_name(HISTORY_SUBCMD)
+                        .visible_aliases(HISTORY_ALIASES)
+                        .about("Display parameter history")
+                        .arg(key_arg().help("Parameter name (optional)").required(false))
+                        .arg(as_of_arg()
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Date/time (or tag) of parameter history, or two date/times for a window"))
+                        .arg(values_flag().help("Display the values set by each change"))
+                        .arg(secrets_display_flag().help("Display secret values"))
+                        .arg(table_format_options().help("Format for the parameter history")),
+                    SubCommand::with_name("restore")
+                        .visible_aliases(&["rest", "re"])
+                        .about("Restore parameter value(s) from an earlier date/time or tag")
+                        .arg(key_arg()
+                            .required(false)
+                            .required_unless("all")
+                            .help("Name of parameter to restore"))
+                        .arg(as_of_arg()
+                            .required(true)
+                            .help("Date/time (or tag) of the value(s) to restore"))
+                        .arg(Arg::with_name("ENV")
+                            .short("e")
+                            .long("env")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Environment(s) to restore (defaults to the current environment)"))
+                        .arg(Arg::with_name("all")
+                            .long("all")
+                            .conflicts_with(KEY_ARG)
+                            .help("Restore all the parameter values in the environment(s)"))
+                        .arg(Arg::with_name("preview")
+                            .long("preview")
+                            .help("Show the changes without saving anything"))
+                        .arg(confirm_flag())
+                        .arg(secrets_display_flag().help("Display secret values"))
+                        .arg(table_format_options().help("Format for the restore changes")),
                     SubCommand::with_name(PUSH_SUBCMD)
                         .visible_aliases(PUSH_ALIASES)
                         .about("Show push task steps for parameters")
@@ -1090,6 +1351,47 @@ pub fn build_cli() -> App<'static, 'static> {
                     .about("Validate a CloudTruth template"),
             ])
         )
+        .subcommand(
+            SubCommand::with_name("lock")
+                .about("Record hashes of the parameter values, for verifying later runs and exports")
+                .arg(Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .help("Lock file to write [default: cloudtruth.lock]"))
+                .arg(param_as_of_arg())
+        )
+        .subcommand(
+            SubCommand::with_name("shell-hook")
+                .about("Print a shell hook that loads the parameters in directories with a .cloudtruth.yaml file")
+                .after_help(concat!(
+                    "Add 'eval \"$(cloudtruth shell-hook bash)\"' to ~/.bashrc (or zsh to ~/.zshrc), or ",
+                    "'cloudtruth shell-hook fish | source' to ~/.config/fish/config.fish.\n\n",
+                    "The .cloudtruth.yaml file in the current directory (or its closest parent) sets the ",
+                    "'project', and optionally the 'environment' and 'profile'. The parameters are ",
+                    "exported on entering the directory, and unset on leaving it.\n\n",
+                    "A .cloudtruth.yaml file is only loaded after it is approved with ",
+                    "'cloudtruth shell-hook allow', and again after every change to it."
+                ))
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .subcommand(SubCommand::with_name("allow")
+                    .about("Approve the current contents of a .cloudtruth.yaml file, so the shell hook loads it")
+                    .arg(Arg::with_name("PATH")
+                        .index(1)
+                        .help("Project file to approve [default: the closest .cloudtruth.yaml]")))
+                .arg(Arg::with_name("SHELL")
+                    .required(true)
+                    .possible_values(&["bash", "zsh", "fish"])
+                    .index(1))
+                .arg(Arg::with_name("ttl")
+                    .long("ttl")
+                    .takes_value(true)
+                    .help("How long loaded values are used before they are reloaded [default: 5m]"))
+                .arg(Arg::with_name("update")
+                    .long("update")
+                    .hidden(true))
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .visible_aliases(&["run", "ru", "r"])
@@ -1137,8 +1439,79 @@ pub fn build_cli() -> App<'static, 'static> {
                     Arg::with_name("strict")
                         .long("strict")
                         .help("Fail when any parameters are unset"),
+                    Arg::with_name("layer")
+                        .long("layer")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PROJECT[:ENV]")
+                        .help(concat!(
+                            "Layer the values from another project (and environment) over the base ",
+                            "project, in the order given -- later layers take precedence, and ",
+                            "conflicting values are reported"
+                        )),
+                    Arg::with_name("layer-strict")
+                        .long("layer-strict")
+                        .requires("layer")
+                        .help("Fail when layers have different values for the same parameter"),
+                    Arg::with_name("template")
+                        .long("template")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("NAME=PATH")
+                        .help("Write the evaluated template to PATH (mode 0600, must not exist) for the run -- may be specified multiple times"),
+                    Arg::with_name("template-dir")
+                        .long("template-dir")
+                        .help(concat!(
+                            "Write templates to a private temporary directory (relative PATH values ",
+                            "are placed there), exported as CLOUDTRUTH_TEMPLATE_DIR"
+                        )),
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .conflicts_with_all(&["exec", "watch"])
+                        .help("Show the variables the command would get (and where they come from), without running it"),
+                    table_format_options().help("Format for the --dry-run variables"),
+                    // '-s' is already used by '--set'
+                    Arg::with_name(SECRETS_FLAG)
+                        .long(SECRETS_FLAG)
+                        .help("Display the secret values with --dry-run"),
+                    Arg::with_name("mask-output")
+                        .long("mask-output")
+                        .help("Replace secret values (and their base64/URL/JSON encoded forms) in the command output with '*****'"),
+                    Arg::with_name("exec")
+                        .long("exec")
+                        .conflicts_with_all(&["watch", "template", "template-dir", "mask-output"])
+                        .help("Replace the CLI process with the command, instead of running a sub-process (Unix only)"),
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .help("Restart the command when the CloudTruth parameter values change"),
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .takes_value(true)
+                        .requires("watch")
+                        .help("How often to check for parameter changes [default: 30s]"),
+                    Arg::with_name("restart-signal")
+                        .long("restart-signal")
+                        .takes_value(true)
+                        .requires("watch")
+                        .case_insensitive(true)
+                        .possible_values(&["TERM", "INT", "HUP", "QUIT", "USR1", "USR2", "KILL"])
+                        .help("Signal sent to stop the command for a restart [default: TERM]"),
+                    Arg::with_name("grace-period")
+                        .long("grace-period")
+                        .takes_value(true)
+                        .requires("watch")
+                        .help("Time for the command to exit before it is killed on restart [default: 10s]"),
+                    Arg::with_name("locked")
+                        .long("locked")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with_all(&[AS_OF_ARG, "layer", "watch"])
+                        .help("Use the values at the lock file tag/time, and refuse to run when they do not match the lock file"),
                     param_as_of_arg(),
                 ])
+                .args(&key_transform_args())
         )
         .subcommand(
             SubCommand::with_name("projects")
@@ -1434,6 +1807,35 @@ pub fn build_cli() -> App<'static, 'static> {
                     ])
             ])
         )
+        .subcommand(SubCommand::with_name("grants")
+            .visible_aliases(&["grant", "gra"])
+            .about("Manage CloudTruth role grants for users and groups")
+            .subcommands(vec![
+                SubCommand::with_name(DELETE_SUBCMD)
+                    .visible_aliases(DELETE_ALIASES)
+                    .about("Delete the grant for a user/group on a project/environment")
+                    .args(&grant_principal_args(true))
+                    .args(&grant_scope_args(true))
+                    .arg(confirm_flag()),
+                SubCommand::with_name(LIST_SUBCMD)
+                    .visible_aliases(LIST_ALIASES)
+                    .about(concat!(
+                        "List CloudTruth grants. When filtered by user, shows the effective ",
+                        "role per scope including grants received through groups."
+                    ))
+                    .args(&grant_principal_args(false))
+                    .args(&grant_scope_args(false))
+                    .arg(role_arg().help("Only show grants with the specified role"))
+                    .arg(show_times_arg())
+                    .arg(table_format_options().help("Format for grant values data")),
+                SubCommand::with_name(SET_SUBCMD)
+                    .visible_aliases(SET_ALIASES)
+                    .about("Create/update the grant for a user/group on a project/environment")
+                    .args(&grant_principal_args(true))
+                    .args(&grant_scope_args(true))
+                    .arg(role_arg().required(true).help("Role to grant")),
+            ])
+        )
         .subcommand(SubCommand::with_name("groups")
             .visible_aliases(&["group", "grp", "gr", "g"])
             .about("Manage CloudTruth user groups ")
@@ -1557,6 +1959,19 @@ pub fn build_cli() -> App<'static, 'static> {
                             .takes_value(true)
                             .multiple(true)
                             .help("Parameters from the file to treat as secrets"),
+                        Arg::with_name("input-format")
+                            .long("input-format")
+                            .takes_value(true)
+                            .possible_values(&["json", "yaml", "properties", "tfvars", "kubernetes"])
+                            .help(concat!(
+                                "Parse the file in this format, instead of on the server -- nested ",
+                                "keys are flattened, and kubernetes Secret values are imported as secrets"
+                            )),
+                        Arg::with_name("delimiter")
+                            .long("delimiter")
+                            .takes_value(true)
+                            .requires("input-format")
+                            .help("Delimiter for joining nested keys [default: .]"),
                         secrets_display_flag().help("Display secret values"),
                         table_format_options().help("Format for imported parameter"),
                         show_times_arg().help("Show import values created times")
@@ -1671,7 +2086,99 @@ pub fn build_cli() -> App<'static, 'static> {
                     .args(&[
                         serialized_format_arg().help("Backup snapshot format"),
                         confirm_flag(),
-                    ])
+                        Arg::with_name("project")
+                            .long("project")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Only include the specified project(s)"),
+                        Arg::with_name("environment")
+                            .long("environment")
+                            .alias("env")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Only include the specified environment(s), and their values"),
+                        Arg::with_name("type")
+                            .long("type")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("Only include the specified parameter type(s)"),
+                        Arg::with_name("output")
+                            .long("output")
+                            .value_name("dir")
+                            .takes_value(true)
+                            .help("Write one file per project, along with a manifest, to this directory"),
+                        Arg::with_name("since")
+                            .long("since")
+                            .value_name("manifest")
+                            .takes_value(true)
+                            .requires("output")
+                            .help("Only write the projects that changed since a previous snapshot manifest"),
+                        Arg::with_name("encrypt")
+                            .long("encrypt")
+                            .help("Encrypt the snapshot using a passphrase (prompted), or a key file"),
+                        Arg::with_name("algorithm")
+                            .long("algorithm")
+                            .takes_value(true)
+                            .possible_values(&["aes_gcm", "chacha20"])
+                            .requires("encrypt")
+                            .help("Encryption algorithm [default: aes_gcm]"),
+                        key_file_opt()
+                            .requires("encrypt")
+                            .help("File containing the encryption key material (instead of a passphrase)"),
+                    ]),
+                SubCommand::with_name("restore")
+                    .visible_aliases(&["rest", "re"])
+                    .about("Restore project/environment/type data from a backup snapshot")
+                    .args(&[
+                        Arg::with_name("file")
+                            .long("file")
+                            .required(true)
+                            .takes_value(true)
+                            .help("Backup snapshot file (YAML or JSON)"),
+                        Arg::with_name("preview")
+                            .long("preview")
+                            .help("Show the changes without saving anything"),
+                        table_format_options().help("Format for restore changes"),
+                        secrets_display_flag().help("Display secret values"),
+                        confirm_flag(),
+                        key_file_opt().help("File containing the key material for an encrypted snapshot"),
+                    ]),
+                SubCommand::with_name(DIFF_SUBCMD)
+                    .visible_aliases(DIFF_ALIASES)
+                    .about("Show differences between two backup snapshots")
+                    .args(&[
+                        Arg::with_name("old")
+                            .required(true)
+                            .index(1)
+                            .help("Older backup snapshot file (YAML or JSON)"),
+                        Arg::with_name("new")
+                            .required(true)
+                            .index(2)
+                            .help("Newer backup snapshot file (YAML or JSON)"),
+                        Arg::with_name("lines")
+                            .long("context")
+                            .short("c")
+                            .takes_value(true)
+                            .default_value("3")
+                            .help("Number of lines of difference context for multi-line values"),
+                        table_format_options().help("Format for snapshot differences"),
+                        secrets_display_flag().help("Display secret values"),
+                        key_file_opt().help("File containing the key material for encrypted snapshots"),
+                    ]),
+                SubCommand::with_name("decrypt")
+                    .visible_aliases(&["dec"])
+                    .about("Decrypt an encrypted backup snapshot for inspection")
+                    .args(&[
+                        Arg::with_name("file")
+                            .long("file")
+                            .required(true)
+                            .takes_value(true)
+                            .help("Encrypted backup snapshot file"),
+                        key_file_opt().help("File containing the key material (instead of a passphrase)"),
+                    ]),
             ])
         )
 }
@@ -0,0 +1,130 @@
+use crate::database::BackupSnapshotDetails;
+use cloudtruth_restapi::models::BackupProject;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Location and digest of a single project in a `BackupManifest`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifestEntry {
+    pub digest: String,
+    /// Snapshot file holding the project, relative to the directory of the manifest.
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+/// Describes a snapshot that was written as a set of files.
+///
+/// The digests cover everything in the (filtered) snapshot, even when only the changed objects
+/// were written. That allows a manifest to be used as the baseline for the next incremental
+/// snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub timestamp: String,
+    #[serde(default)]
+    pub since: Option<String>,
+    /// File holding all the environments and parameter types (changed or not).
+    #[serde(default)]
+    pub shared: Option<String>,
+    #[serde(default)]
+    pub environments: BTreeMap<String, String>,
+    #[serde(default)]
+    pub types: BTreeMap<String, String>,
+    #[serde(default)]
+    pub projects: BTreeMap<String, BackupManifestEntry>,
+}
+
+/// Computes a digest of the serialized `item`, which is used to detect changes between snapshots.
+pub fn backup_digest<T: Serialize>(item: &T) -> String {
+    // NOTE: serde_json::Value objects are sorted by key, so HashMap ordering does not matter
+    let value = serde_json::to_value(item).unwrap_or_default();
+    hex::encode(Sha256::digest(value.to_string().as_bytes()))
+}
+
+impl From<&BackupSnapshotDetails> for BackupManifest {
+    fn from(snapshot: &BackupSnapshotDetails) -> Self {
+        Self {
+            timestamp: snapshot.timestamp.clone(),
+            since: None,
+            shared: None,
+            environments: snapshot
+                .environments
+                .values()
+                .map(|e| (e.name.clone(), backup_digest(e)))
+                .collect(),
+            types: snapshot
+                .types
+                .values()
+                .map(|t| (t.name.clone(), backup_digest(t)))
+                .collect(),
+            projects: snapshot
+                .projects
+                .values()
+                .map(|p| {
+                    let entry = BackupManifestEntry {
+                        digest: backup_digest(p),
+                        file: None,
+                    };
+                    (p.name.clone(), entry)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl BackupManifest {
+    /// The file holding the `project`, when it has the same digest in this manifest.
+    pub fn unchanged_file(&self, project: &BackupProject) -> Option<&str> {
+        self.projects
+            .get(&project.name)
+            .filter(|e| e.digest == backup_digest(project))
+            .and_then(|e| e.file.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloudtruth_restapi::models::BackupEnvironment;
+    use std::collections::HashMap;
+
+    fn snapshot(env_desc: &str, proj_desc: &str) -> BackupSnapshotDetails {
+        let mut environments = HashMap::new();
+        for name in ["default", "production", "staging"] {
+            let mut env = BackupEnvironment::new(name.to_string());
+            env.description = Some(format!("{name} {env_desc}"));
+            environments.insert(name.to_string(), env);
+        }
+        let mut projects = HashMap::new();
+        for name in ["proj1", "proj2"] {
+            let mut proj = BackupProject::new(HashMap::new(), HashMap::new(), name.to_string());
+            proj.description = Some(format!("{name} {proj_desc}"));
+            projects.insert(name.to_string(), proj);
+        }
+        BackupSnapshotDetails::new(environments, HashMap::new(), projects, "now".to_string())
+    }
+
+    #[test]
+    fn digest_is_stable() {
+        let first = snapshot("a", "b");
+        let second = snapshot("a", "b");
+        assert_eq!(backup_digest(&first), backup_digest(&second));
+        assert_ne!(backup_digest(&first), backup_digest(&snapshot("a", "c")));
+    }
+
+    #[test]
+    fn unchanged_file() {
+        let mut manifest = BackupManifest::from(&snapshot("a", "b"));
+        manifest.projects.get_mut("proj1").unwrap().file = Some("proj1.yaml".to_string());
+        let same = snapshot("a", "b");
+        assert_eq!(
+            manifest.unchanged_file(&same.projects["proj1"]),
+            Some("proj1.yaml")
+        );
+        // no file was recorded
+        assert_eq!(manifest.unchanged_file(&same.projects["proj2"]), None);
+
+        let changed = snapshot("a", "c");
+        assert_eq!(manifest.unchanged_file(&changed.projects["proj1"]), None);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::database::{CryptoAlgorithm, CryptoError};
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::Aes256Gcm;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::str::FromStr;
+
+const ENVELOPE_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 32;
+const HKDF_INFO: &[u8] = b"cloudtruth-cli envelope";
+/// Upper bounds for the Argon2 parameters read from an envelope (1 GiB of memory), so a crafted
+/// file cannot exhaust the memory or take forever to decrypt.
+const ARGON2_MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const ARGON2_MAX_ITERATIONS: u32 = 100;
+const ARGON2_MAX_PARALLELISM: u32 = 16;
+
+/// Identifies a parameter value that holds an encoded envelope.
+const VALUE_PREFIX: &str = "ctenvelope:";
+
+/// The function used to derive the encryption key from the key material.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name")]
+pub enum CryptoKdf {
+    /// For high-entropy key material, like key files and API keys.
+    #[serde(rename = "hkdf-sha256")]
+    HkdfSha256,
+    /// For passphrases, where the work factor slows down guessing.
+    #[serde(rename = "argon2id")]
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for CryptoKdf {
+    /// Envelopes written before the KDF was recorded always used HKDF.
+    fn default() -> Self {
+        Self::HkdfSha256
+    }
+}
+
+impl CryptoKdf {
+    /// The Argon2id parameters used for passphrases (the OWASP recommended minimum).
+    pub fn passphrase() -> Self {
+        Self::Argon2id {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+
+    fn derive_key(&self, key_material: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN], CryptoError> {
+        let mut key = [0u8; KEY_LEN];
+        match self {
+            Self::HkdfSha256 => {
+                let hkdf = Hkdf::<Sha256>::new(Some(salt), key_material);
+                hkdf.expand(HKDF_INFO, &mut key)
+                    .map_err(|_| CryptoError::EncryptionFailed)?;
+            }
+            Self::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                if *memory_kib > ARGON2_MAX_MEMORY_KIB {
+                    return Err(CryptoError::InvalidEnvelope(format!(
+                        "argon2id memory {memory_kib} KiB exceeds {ARGON2_MAX_MEMORY_KIB} KiB"
+                    )));
+                }
+                if *iterations > ARGON2_MAX_ITERATIONS {
+                    return Err(CryptoError::InvalidEnvelope(format!(
+                        "argon2id iterations {iterations} exceeds {ARGON2_MAX_ITERATIONS}"
+                    )));
+                }
+                if *parallelism > ARGON2_MAX_PARALLELISM {
+                    return Err(CryptoError::InvalidEnvelope(format!(
+                        "argon2id parallelism {parallelism} exceeds {ARGON2_MAX_PARALLELISM}"
+                    )));
+                }
+                let params = Params::new(*memory_kib, *iterations, *parallelism, Some(KEY_LEN))
+                    .map_err(|e| CryptoError::InvalidEnvelope(format!("argon2id: {e}")))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(key_material, salt, &mut key)
+                    .map_err(|_| CryptoError::EncryptionFailed)?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// Self-describing ciphertext.
+///
+/// The encryption key is derived (using the recorded KDF) from the provided key material and a
+/// random salt, so the same key material never encrypts two envelopes with the same key. The
+/// binary fields are base64 encoded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CryptoEnvelope {
+    pub version: u32,
+    pub algorithm: String,
+    #[serde(default)]
+    pub kdf: CryptoKdf,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn decode_field(name: &str, value: &str) -> Result<Vec<u8>, CryptoError> {
+    base64::decode(value).map_err(|e| CryptoError::InvalidEnvelope(format!("{name}: {e}")))
+}
+
+impl CryptoEnvelope {
+    /// Encrypts the `plaintext` using a key derived from the `key_material` by the `kdf`.
+    pub fn encrypt(
+        algorithm: &CryptoAlgorithm,
+        kdf: &CryptoKdf,
+        key_material: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Self, CryptoError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let key = kdf.derive_key(key_material, &salt)?;
+        let ciphertext = match algorithm {
+            CryptoAlgorithm::AesGcm => Aes256Gcm::new(aes_gcm::Key::from_slice(&key))
+                .encrypt(aes_gcm::Nonce::from_slice(&nonce), plaintext),
+            CryptoAlgorithm::ChaCha20 => {
+                ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key))
+                    .encrypt(chacha20poly1305::Nonce::from_slice(&nonce), plaintext)
+            }
+            CryptoAlgorithm::Unknown => {
+                return Err(CryptoError::UnsupportedAlgorithm(algorithm.to_string()))
+            }
+        }
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            algorithm: algorithm.to_string(),
+            kdf: kdf.clone(),
+            salt: base64::encode(salt),
+            nonce: base64::encode(nonce),
+            ciphertext: base64::encode(ciphertext),
+        })
+    }
+
+    /// Decrypts the envelope using a key derived from the `key_material` by the recorded KDF.
+    pub fn decrypt(&self, key_material: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(CryptoError::InvalidEnvelope(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+        let salt = decode_field("salt", &self.salt)?;
+        let nonce = decode_field("nonce", &self.nonce)?;
+        let ciphertext = decode_field("ciphertext", &self.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(CryptoError::InvalidEnvelope(format!(
+                "nonce length {}",
+                nonce.len()
+            )));
+        }
+        let key = self.kdf.derive_key(key_material, &salt)?;
+        match CryptoAlgorithm::from_str(&self.algorithm) {
+            Ok(CryptoAlgorithm::AesGcm) => Aes256Gcm::new(aes_gcm::Key::from_slice(&key))
+                .decrypt(aes_gcm::Nonce::from_slice(&nonce), ciphertext.as_ref()),
+            Ok(CryptoAlgorithm::ChaCha20) => {
+                ChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key)).decrypt(
+                    chacha20poly1305::Nonce::from_slice(&nonce),
+                    ciphertext.as_ref(),
+                )
+            }
+            _ => return Err(CryptoError::UnsupportedAlgorithm(self.algorithm.clone())),
+        }
+        .map_err(|_| CryptoError::DecryptionFailed)
+    }
+
+    /// Encodes the envelope as a single-line string, so it can be stored as a parameter value.
+    pub fn to_value(&self) -> String {
+        let json = serde_json::to_string(self).unwrap();
+        format!("{VALUE_PREFIX}{}", base64::encode(json))
+    }
+
+    /// Parses a parameter value created by `to_value()`, or returns `None` for any other value.
+    pub fn from_value(value: &str) -> Option<Self> {
+        let encoded = value.trim().strip_prefix(VALUE_PREFIX)?;
+        let json = base64::decode(encoded).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+/// Replaces every encoded envelope in the `text` with its plaintext, after passing it through
+/// `escape`.
+///
+/// The `text` may be a single parameter value, or an export containing many values.
+pub fn decrypt_envelopes(
+    text: &str,
+    key_material: &[u8],
+    escape: impl Fn(&str) -> String,
+) -> Result<String, CryptoError> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(VALUE_PREFIX) {
+        result.push_str(&rest[..start]);
+        let encoded = &rest[start + VALUE_PREFIX.len()..];
+        let end = encoded
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+/=".contains(c)))
+            .unwrap_or(encoded.len());
+        let token = &rest[start..start + VALUE_PREFIX.len() + end];
+        match CryptoEnvelope::from_value(token) {
+            Some(envelope) => {
+                let plaintext = envelope.decrypt(key_material)?;
+                result.push_str(&escape(&String::from_utf8_lossy(&plaintext)));
+            }
+            None => result.push_str(token),
+        }
+        rest = &rest[start + token.len()..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let plaintext = b"my secret snapshot";
+        // cheap Argon2id parameters, to keep the test fast
+        let argon2 = CryptoKdf::Argon2id {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        for kdf in [CryptoKdf::HkdfSha256, argon2] {
+            for algorithm in [CryptoAlgorithm::AesGcm, CryptoAlgorithm::ChaCha20] {
+                let envelope =
+                    CryptoEnvelope::encrypt(&algorithm, &kdf, b"passphrase", plaintext).unwrap();
+                assert_eq!(envelope.algorithm, algorithm.to_string());
+                assert_eq!(envelope.kdf, kdf);
+                assert_ne!(envelope.ciphertext, base64::encode(plaintext));
+                assert_eq!(envelope.decrypt(b"passphrase").unwrap(), plaintext);
+                assert!(envelope.decrypt(b"wrong").is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn recorded_kdf() {
+        let envelope = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::passphrase(),
+            b"passphrase",
+            b"text",
+        )
+        .unwrap();
+        let yaml = serde_yaml::to_string(&envelope).unwrap();
+        assert!(yaml.contains("name: argon2id"));
+        assert!(yaml.contains("memory_kib: 19456"));
+        let loaded: CryptoEnvelope = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(loaded.decrypt(b"passphrase").unwrap(), b"text");
+
+        // the parameters come from the envelope, so they must be sane
+        let greedy = [
+            (u32::MAX, 2, 1),
+            (19 * 1024, u32::MAX, 1),
+            (19 * 1024, 2, u32::MAX),
+        ];
+        for (memory_kib, iterations, parallelism) in greedy {
+            let mut crafted = loaded.clone();
+            crafted.kdf = CryptoKdf::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            };
+            assert!(matches!(
+                crafted.decrypt(b"passphrase"),
+                Err(CryptoError::InvalidEnvelope(_))
+            ));
+        }
+
+        // envelopes without a recorded KDF used HKDF-SHA256
+        let envelope = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        )
+        .unwrap();
+        let mut json = serde_json::to_value(envelope).unwrap();
+        json.as_object_mut().unwrap().remove("kdf");
+        let legacy: CryptoEnvelope = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.kdf, CryptoKdf::HkdfSha256);
+        assert_eq!(legacy.decrypt(b"key").unwrap(), b"text");
+    }
+
+    #[test]
+    fn unique_salt_and_nonce() {
+        let first = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        )
+        .unwrap();
+        let second = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        )
+        .unwrap();
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let envelope = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        )
+        .unwrap();
+        let value = envelope.to_value();
+        assert!(!value.contains('\n'));
+        assert_eq!(CryptoEnvelope::from_value(&value), Some(envelope));
+        assert_eq!(CryptoEnvelope::from_value("plain value"), None);
+        assert_eq!(CryptoEnvelope::from_value("ctenvelope:bogus"), None);
+    }
+
+    #[test]
+    fn decrypt_embedded_envelopes() {
+        let first = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"it's",
+        )
+        .unwrap();
+        let second = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::ChaCha20,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"two",
+        )
+        .unwrap();
+        let text = format!(
+            "A='{}'\nB='{}'\nC='ctenvelope:'\n",
+            first.to_value(),
+            second.to_value()
+        );
+        let escape = |s: &str| s.replace('\'', "'\\''");
+        let result = decrypt_envelopes(&text, b"key", escape).unwrap();
+        assert_eq!(result, "A='it'\\''s'\nB='two'\nC='ctenvelope:'\n");
+        assert!(decrypt_envelopes(&text, b"wrong", escape).is_err());
+        assert_eq!(
+            decrypt_envelopes("no envelopes", b"key", |s| s.to_string()).unwrap(),
+            "no envelopes"
+        );
+    }
+
+    #[test]
+    fn unknown_algorithm() {
+        let result = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::Unknown,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        );
+        assert!(result.is_err());
+        let mut envelope = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::ChaCha20,
+            &CryptoKdf::HkdfSha256,
+            b"key",
+            b"text",
+        )
+        .unwrap();
+        envelope.algorithm = "rot13".to_string();
+        assert!(envelope.decrypt(b"key").is_err());
+    }
+}
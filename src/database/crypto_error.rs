@@ -0,0 +1,28 @@
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(Debug)]
+pub enum CryptoError {
+    UnsupportedAlgorithm(String),
+    EncryptionFailed,
+    DecryptionFailed,
+    InvalidEnvelope(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::UnsupportedAlgorithm(name) => {
+                write!(f, "Unsupported encryption algorithm '{name}'")
+            }
+            CryptoError::EncryptionFailed => write!(f, "Encryption failed"),
+            CryptoError::DecryptionFailed => {
+                write!(f, "Decryption failed (wrong key or corrupted data)")
+            }
+            CryptoError::InvalidEnvelope(msg) => write!(f, "Invalid encrypted envelope: {msg}"),
+        }
+    }
+}
+
+impl error::Error for CryptoError {}
@@ -0,0 +1,126 @@
+use cloudtruth_restapi::models::Grant;
+use std::collections::HashMap;
+
+/// Maps user, group, project and environment URLs to their names.
+pub type GrantNameMap = HashMap<String, String>;
+
+#[derive(Clone, Debug)]
+pub struct GrantDetails {
+    pub id: String,
+    pub url: String,
+    pub principal_url: String,
+    pub principal_name: String,
+    pub principal_type: String,
+    pub scope_url: String,
+    pub scope_name: String,
+    pub scope_type: String,
+    pub role: String,
+
+    // how the principal received the role (direct, or through a group)
+    pub source: String,
+
+    pub created_at: String,
+    pub modified_at: String,
+}
+
+/// Gets the object type from a REST URL (e.g. `.../api/v1/users/<id>/` is a `user`).
+fn type_from_url(url: &str) -> String {
+    let kinds = [
+        ("/users/", "user"),
+        ("/groups/", "group"),
+        ("/projects/", "project"),
+        ("/environments/", "environment"),
+    ];
+    for (segment, kind) in kinds {
+        if url.contains(segment) {
+            return kind.to_string();
+        }
+    }
+    "unknown".to_string()
+}
+
+/// Ranks the roles, so the most permissive one can be determined.
+pub fn role_rank(role: &str) -> u8 {
+    match role {
+        "OWNER" => 4,
+        "ADMIN" => 3,
+        "CONTRIB" => 2,
+        "VIEWER" => 1,
+        _ => 0,
+    }
+}
+
+impl GrantDetails {
+    pub fn new(grant: &Grant, name_map: &GrantNameMap) -> Self {
+        let name_from = |url: &String| name_map.get(url).cloned().unwrap_or_else(|| url.clone());
+        Self {
+            id: grant.id.clone(),
+            url: grant.url.clone(),
+            principal_url: grant.principal.clone(),
+            principal_name: name_from(&grant.principal),
+            principal_type: type_from_url(&grant.principal),
+            scope_url: grant.scope.clone(),
+            scope_name: name_from(&grant.scope),
+            scope_type: type_from_url(&grant.scope),
+            role: grant
+                .role
+                .as_ref()
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+            source: "direct".to_string(),
+            created_at: grant.created_at.clone(),
+            modified_at: grant.modified_at.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn get_property(&self, property_name: &str) -> String {
+        match property_name {
+            "id" => self.id.clone(),
+            "principal" => self.principal_name.clone(),
+            "principal-type" => self.principal_type.clone(),
+            "scope" => self.scope_name.clone(),
+            "scope-type" => self.scope_type.clone(),
+            "role" => self.role.to_lowercase(),
+            "source" => self.source.clone(),
+            "created-at" => self.created_at.clone(),
+            "modified-at" => self.modified_at.clone(),
+            _ => format!("Unhandled property name '{property_name}'"),
+        }
+    }
+
+    pub fn get_properties(&self, fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|p| self.get_property(p)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloudtruth_restapi::models::RoleEnum;
+
+    #[test]
+    fn grant_details_names_and_types() {
+        let user_url = "https://localhost/api/v1/users/abc/".to_string();
+        let env_url = "https://localhost/api/v1/environments/def/".to_string();
+        let grant = Grant::new(
+            "https://localhost/api/v1/grants/123/".to_string(),
+            "123".to_string(),
+            user_url.clone(),
+            env_url,
+            Some(RoleEnum::CONTRIB),
+            "2023-01-01T00:00:00Z".to_string(),
+            None,
+        );
+        let mut name_map = GrantNameMap::new();
+        name_map.insert(user_url, "someone".to_string());
+        let details = GrantDetails::new(&grant, &name_map);
+        assert_eq!(
+            details.get_properties(&["principal", "principal-type", "scope-type", "role"]),
+            vec!["someone", "user", "environment", "contrib"]
+        );
+        // unmapped URLs are shown as-is
+        assert_eq!(details.scope_name, details.scope_url);
+        assert!(role_rank("OWNER") > role_rank("ADMIN"));
+        assert!(role_rank("CONTRIB") > role_rank("VIEWER"));
+    }
+}
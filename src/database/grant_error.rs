@@ -0,0 +1,24 @@
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+
+#[derive(Debug)]
+pub enum GrantError {
+    InvalidRole(String),
+    Authentication(String),
+    ResponseError(String),
+    UnhandledError(String),
+}
+
+impl fmt::Display for GrantError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GrantError::InvalidRole(role) => write!(f, "Invalid role value: '{role}'"),
+            GrantError::Authentication(msg) => write!(f, "Not Authenticated: {msg}"),
+            GrantError::ResponseError(msg) => write!(f, "{msg}"),
+            GrantError::UnhandledError(msg) => write!(f, "Unhandled error: {msg}"),
+        }
+    }
+}
+
+impl error::Error for GrantError {}
@@ -0,0 +1,173 @@
+use cloudtruth_restapi::apis::grants_api::{
+    grants_create, grants_destroy, grants_list, grants_partial_update,
+};
+use cloudtruth_restapi::apis::Error::ResponseError;
+use cloudtruth_restapi::models::{Grant, PatchedGrant, RoleEnum};
+
+use crate::database::{
+    auth_details, page_size, response_message, GrantDetails, GrantError, GrantNameMap,
+    OpenApiConfig,
+};
+
+const NO_ORDERING: Option<&str> = None;
+
+pub struct Grants {}
+
+fn auth_error(content: &str) -> GrantError {
+    GrantError::Authentication(auth_details(content))
+}
+
+fn response_error(status: &reqwest::StatusCode, content: &str) -> GrantError {
+    match status.as_u16() {
+        401 => auth_error(content),
+        403 => auth_error(content),
+        _ => GrantError::ResponseError(response_message(status, content)),
+    }
+}
+
+fn to_role_enum(value: &str) -> Result<RoleEnum, GrantError> {
+    match value.to_uppercase().as_str() {
+        "OWNER" => Ok(RoleEnum::OWNER),
+        "ADMIN" => Ok(RoleEnum::ADMIN),
+        "CONTRIB" => Ok(RoleEnum::CONTRIB),
+        "VIEWER" => Ok(RoleEnum::VIEWER),
+        _ => Err(GrantError::InvalidRole(value.to_string())),
+    }
+}
+
+impl Grants {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Gets the grants, optionally filtered by the principal/scope URLs and role.
+    ///
+    /// The server only returns direct grants for a principal (e.g. not the grants a user
+    /// receives through a group).
+    pub fn get_grants_list(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        principal_url: Option<&str>,
+        scope_url: Option<&str>,
+        role: Option<&str>,
+    ) -> Result<Vec<Grant>, GrantError> {
+        /* Need to decode urlencoding from the URLs to avoid double-encoding in the REST client */
+        let principal = principal_url.map(|u| urlencoding::decode(u).unwrap().to_string());
+        let scope = scope_url.map(|u| urlencoding::decode(u).unwrap().to_string());
+        let role = role.map(|r| r.to_uppercase());
+        let mut page_count = 1;
+        let mut result: Vec<Grant> = Vec::new();
+        loop {
+            let response = grants_list(
+                rest_cfg,
+                NO_ORDERING,
+                Some(page_count),
+                page_size(rest_cfg),
+                principal.as_deref(),
+                role.as_deref(),
+                scope.as_deref(),
+            );
+            match response {
+                Ok(data) => {
+                    if let Some(grants) = data.results {
+                        result.extend(grants);
+                        page_count += 1;
+                    } else {
+                        break;
+                    }
+                    if data.next.is_none() || data.next.as_ref().unwrap().is_empty() {
+                        break;
+                    }
+                }
+                Err(ResponseError(ref content)) => {
+                    return Err(response_error(&content.status, &content.content))
+                }
+                Err(e) => return Err(GrantError::UnhandledError(e.to_string())),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn get_grant_details(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        principal_url: Option<&str>,
+        scope_url: Option<&str>,
+        role: Option<&str>,
+        name_map: &GrantNameMap,
+    ) -> Result<Vec<GrantDetails>, GrantError> {
+        let grants = self.get_grants_list(rest_cfg, principal_url, scope_url, role)?;
+        Ok(grants
+            .iter()
+            .map(|g| GrantDetails::new(g, name_map))
+            .collect())
+    }
+
+    /// Gets the grant identifier for the principal in the scope (if any).
+    pub fn get_id(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        principal_url: &str,
+        scope_url: &str,
+    ) -> Result<Option<String>, GrantError> {
+        let grants = self.get_grants_list(rest_cfg, Some(principal_url), Some(scope_url), None)?;
+        Ok(grants.first().map(|g| g.id.clone()))
+    }
+
+    pub fn create_grant(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        principal_url: &str,
+        scope_url: &str,
+        role: &str,
+    ) -> Result<String, GrantError> {
+        let grant = Grant::new(
+            "".to_string(),
+            "".to_string(),
+            principal_url.to_string(),
+            scope_url.to_string(),
+            Some(to_role_enum(role)?),
+            "".to_string(),
+            None,
+        );
+        let response = grants_create(rest_cfg, grant);
+        match response {
+            Ok(grant) => Ok(grant.id),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(GrantError::UnhandledError(e.to_string())),
+        }
+    }
+
+    pub fn update_grant(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        grant_id: &str,
+        role: &str,
+    ) -> Result<(), GrantError> {
+        let patched = PatchedGrant {
+            role: Some(Box::new(to_role_enum(role)?)),
+            ..Default::default()
+        };
+        let response = grants_partial_update(rest_cfg, grant_id, Some(patched));
+        match response {
+            Ok(_) => Ok(()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(GrantError::UnhandledError(e.to_string())),
+        }
+    }
+
+    pub fn delete_grant(&self, rest_cfg: &OpenApiConfig, grant_id: &str) -> Result<(), GrantError> {
+        let response = grants_destroy(rest_cfg, grant_id);
+        match response {
+            Ok(_) => Ok(()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(GrantError::UnhandledError(e.to_string())),
+        }
+    }
+}
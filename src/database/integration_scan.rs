@@ -0,0 +1,162 @@
+use cloudtruth_restapi::models::{DiscoveredContent, DiscoveryResult};
+use std::collections::HashMap;
+
+/// One row of an integration scan: an external value the resource pattern reached, along with
+/// the project/environment/parameter it would be imported into.
+#[derive(Clone, Debug, Default)]
+pub struct IntegrationScanEntry {
+    pub fqn: String,
+    pub venue_id: String,
+    pub venue_name: String,
+    pub project_name: String,
+    pub environment_name: String,
+    pub parameter_name: String,
+
+    // filled in when the server did not match the external value
+    pub skipped_reason: String,
+
+    // other external values that map to the same project/environment/parameter
+    pub conflicts: Vec<String>,
+}
+
+impl IntegrationScanEntry {
+    pub fn is_skipped(&self) -> bool {
+        !self.skipped_reason.is_empty()
+    }
+
+    pub fn status(&self) -> String {
+        if self.is_skipped() {
+            "skipped".to_string()
+        } else if !self.conflicts.is_empty() {
+            "conflict".to_string()
+        } else {
+            "matched".to_string()
+        }
+    }
+
+    pub fn get_property(&self, property_name: &str) -> String {
+        match property_name {
+            "fqn" => self.fqn.clone(),
+            "venue-id" => self.venue_id.clone(),
+            "venue-name" => self.venue_name.clone(),
+            "project" => self.project_name.clone(),
+            "environment" => self.environment_name.clone(),
+            "parameter" => self.parameter_name.clone(),
+            "status" => self.status(),
+            "details" => {
+                if self.is_skipped() {
+                    self.skipped_reason.clone()
+                } else if !self.conflicts.is_empty() {
+                    format!("Also matched by: {}", self.conflicts.join(", "))
+                } else {
+                    "".to_string()
+                }
+            }
+            _ => format!("Unhandled property name '{property_name}'"),
+        }
+    }
+
+    pub fn get_properties(&self, fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|p| self.get_property(p)).collect()
+    }
+}
+
+impl From<(&str, &DiscoveredContent)> for IntegrationScanEntry {
+    fn from((fqn, content): (&str, &DiscoveredContent)) -> Self {
+        IntegrationScanEntry {
+            fqn: fqn.to_string(),
+            venue_id: content.venue_id.clone(),
+            venue_name: content.venue_name.clone(),
+            project_name: content.project_name.clone(),
+            environment_name: content.environment_name.clone(),
+            parameter_name: content.parameter_name.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Flattens the server `DiscoveryResult` into sorted entries, and flags the matches that would
+/// write the same parameter value.
+pub fn scan_entries(result: &DiscoveryResult) -> Vec<IntegrationScanEntry> {
+    let mut entries: Vec<IntegrationScanEntry> = vec![];
+    for (fqn, contents) in &result.matched {
+        for content in contents {
+            entries.push(IntegrationScanEntry::from((fqn.as_str(), content)));
+        }
+    }
+    mark_conflicts(&mut entries);
+    for (fqn, reason) in &result.skipped {
+        entries.push(IntegrationScanEntry {
+            fqn: fqn.clone(),
+            skipped_reason: reason.clone(),
+            ..Default::default()
+        });
+    }
+    entries.sort_by(|l, r| {
+        l.fqn
+            .cmp(&r.fqn)
+            .then_with(|| l.venue_name.cmp(&r.venue_name))
+    });
+    entries
+}
+
+fn mark_conflicts(entries: &mut [IntegrationScanEntry]) {
+    let target = |e: &IntegrationScanEntry| {
+        (
+            e.project_name.clone(),
+            e.environment_name.clone(),
+            e.parameter_name.clone(),
+        )
+    };
+    let mut sources: HashMap<(String, String, String), Vec<(String, String)>> = HashMap::new();
+    for entry in entries.iter() {
+        sources
+            .entry(target(entry))
+            .or_default()
+            .push((entry.venue_id.clone(), entry.venue_name.clone()));
+    }
+    for entry in entries.iter_mut() {
+        entry.conflicts = sources[&target(entry)]
+            .iter()
+            .filter(|(id, _)| *id != entry.venue_id)
+            .map(|(_, name)| name.clone())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(venue: &str, param: &str) -> DiscoveredContent {
+        DiscoveredContent::new(
+            venue.to_string(),
+            venue.to_string(),
+            "default".to_string(),
+            "proj".to_string(),
+            param.to_string(),
+        )
+    }
+
+    #[test]
+    fn scan_conflicts_and_skips() {
+        let mut matched = HashMap::new();
+        matched.insert(
+            "aws://ssm/".to_string(),
+            vec![
+                content("/prod/a", "a"),
+                content("/prod/b", "shared"),
+                content("/prod/c", "shared"),
+            ],
+        );
+        let mut skipped = HashMap::new();
+        skipped.insert("aws://ssm/bad".to_string(), "no project".to_string());
+        let entries = scan_entries(&DiscoveryResult::new(matched, skipped));
+
+        assert_eq!(entries.len(), 4);
+        let status: Vec<String> = entries.iter().map(|e| e.status()).collect();
+        assert_eq!(status, vec!["matched", "conflict", "conflict", "skipped"]);
+        assert_eq!(entries[1].conflicts, vec!["/prod/c".to_string()]);
+        assert_eq!(entries[3].get_property("details"), "no project");
+    }
+}
@@ -1,13 +1,14 @@
 use crate::database::{
     auth_details, extract_details, last_from_url, page_size, parent_id_from_url, response_message,
-    ActionDetails, IntegrationDetails, IntegrationError, IntegrationNode, OpenApiConfig,
-    TaskDetail, TaskStepDetails, NO_PAGE_COUNT, NO_PAGE_SIZE,
+    scan_entries, ActionDetails, IntegrationDetails, IntegrationError, IntegrationNode,
+    IntegrationScanEntry, OpenApiConfig, TaskDetail, TaskStepDetails, NO_PAGE_COUNT, NO_PAGE_SIZE,
 };
 use cloudtruth_restapi::apis::integrations_api::*;
 use cloudtruth_restapi::apis::Error::ResponseError;
 use cloudtruth_restapi::models::{
-    AwsPull, AwsPullSyncActionRequest, AwsPush, AwsPushUpdate, AwsRegionEnum, AwsServiceEnum,
-    GitHubPull,
+    AwsIntegrationCreate, AwsIntegrationScan, AwsPull, AwsPullSyncActionRequest, AwsPush,
+    AwsPushUpdate, AwsRegionEnum, AwsServiceEnum, AzureKeyVaultIntegrationCreate,
+    AzureKeyVaultIntegrationScan, GitHubIntegrationCreate, GitHubPull,
 };
 
 const NO_ORDERING: Option<&str> = None;
@@ -472,6 +473,234 @@ impl Integrations {
         Ok(())
     }
 
+    ///==========================================
+    /// Integration create/delete
+    ///==========================================
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_aws_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        account_id: &str,
+        role_name: &str,
+        regions: &[String],
+        services: &[String],
+        description: Option<&str>,
+        writable: Option<bool>,
+    ) -> Result<IntegrationDetails, IntegrationError> {
+        let mut aws_regions: Vec<AwsRegionEnum> = vec![];
+        for region in regions {
+            match aws_region_from_str(region) {
+                Some(r) => aws_regions.push(r),
+                None => {
+                    return Err(IntegrationError::NotFound(format!(
+                        "Unknown AWS region '{region}'"
+                    )))
+                }
+            }
+        }
+        let mut aws_services: Vec<AwsServiceEnum> = vec![];
+        for service in services {
+            match aws_service_from_str(service) {
+                Some(s) => aws_services.push(s),
+                None => {
+                    return Err(IntegrationError::NotFound(format!(
+                        "Unknown AWS service '{service}'"
+                    )))
+                }
+            }
+        }
+        let mut integ_create = AwsIntegrationCreate::new(
+            account_id.to_string(),
+            aws_regions,
+            aws_services,
+            role_name.to_string(),
+        );
+        integ_create.description = description.map(String::from);
+        integ_create.writable = writable;
+        let response = integrations_aws_create(rest_cfg, integ_create);
+        match response {
+            Ok(api) => Ok(IntegrationDetails::from(&api)),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    pub fn create_azure_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        tenant_id: &str,
+        vault_name: &str,
+        description: Option<&str>,
+        writable: Option<bool>,
+    ) -> Result<IntegrationDetails, IntegrationError> {
+        let mut integ_create =
+            AzureKeyVaultIntegrationCreate::new(vault_name.to_string(), tenant_id.to_string());
+        integ_create.description = description.map(String::from);
+        integ_create.writable = writable;
+        let response = integrations_azure_key_vault_create(rest_cfg, integ_create);
+        match response {
+            Ok(api) => Ok(IntegrationDetails::from(&api)),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    pub fn create_github_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        installation_id: i32,
+        description: Option<&str>,
+        writable: Option<bool>,
+    ) -> Result<IntegrationDetails, IntegrationError> {
+        let mut integ_create = GitHubIntegrationCreate::new(installation_id);
+        integ_create.description = description.map(String::from);
+        integ_create.writable = writable;
+        let response = integrations_github_create(rest_cfg, integ_create);
+        match response {
+            Ok(api) => Ok(IntegrationDetails::from(&api)),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    fn delete_aws_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        integration_id: &str,
+        in_use: Option<&str>,
+    ) -> Result<(), IntegrationError> {
+        let response = integrations_aws_destroy(rest_cfg, integration_id, in_use);
+        match response {
+            Ok(_) => Ok(()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    fn delete_azure_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        integration_id: &str,
+        in_use: Option<&str>,
+    ) -> Result<(), IntegrationError> {
+        let response = integrations_azure_key_vault_destroy(rest_cfg, integration_id, in_use);
+        match response {
+            Ok(_) => Ok(()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    fn delete_github_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        integration_id: &str,
+        in_use: Option<&str>,
+    ) -> Result<(), IntegrationError> {
+        let response = integrations_github_destroy(rest_cfg, integration_id, in_use);
+        match response {
+            Ok(_) => Ok(()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    /// Deletes the integration, using the provider to pick the right endpoint.
+    ///
+    /// The `in_use` value (fail, leave, remove) determines what the server does with values
+    /// that still reference the integration.
+    pub fn delete_integration(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        details: &IntegrationDetails,
+        in_use: Option<&str>,
+    ) -> Result<(), IntegrationError> {
+        match details.provider.as_str() {
+            "aws" => self.delete_aws_integration(rest_cfg, &details.id, in_use),
+            "azure" => self.delete_azure_integration(rest_cfg, &details.id, in_use),
+            "github" => self.delete_github_integration(rest_cfg, &details.id, in_use),
+            provider => Err(IntegrationError::UnhandledError(format!(
+                "Unhandled integration provider '{provider}'"
+            ))),
+        }
+    }
+
+    ///==========================================
+    /// Integration scan
+    ///==========================================
+    fn scan_aws(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        integration_id: &str,
+        resource: &str,
+        region: &str,
+        service: &str,
+    ) -> Result<Vec<IntegrationScanEntry>, IntegrationError> {
+        let scan = AwsIntegrationScan {
+            region: aws_region_from_str(region).map(Box::new),
+            service: aws_service_from_str(service).map(Box::new),
+            resource: Some(resource.to_string()),
+        };
+        let response = integrations_aws_scan_create(rest_cfg, integration_id, scan);
+        match response {
+            Ok(result) => Ok(scan_entries(&result)),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    fn scan_azure(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        integration_id: &str,
+        resource: &str,
+    ) -> Result<Vec<IntegrationScanEntry>, IntegrationError> {
+        let scan = AzureKeyVaultIntegrationScan {
+            resource: Some(resource.to_string()),
+        };
+        let response = integrations_azure_key_vault_scan_create(rest_cfg, integration_id, scan);
+        match response {
+            Ok(result) => Ok(scan_entries(&result)),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(IntegrationError::UnhandledError(e.to_string())),
+        }
+    }
+
+    /// Asks the server which external values the `resource` pattern matches, without creating
+    /// an import. The `region` and `service` are only used for AWS integrations.
+    pub fn scan(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        details: &IntegrationDetails,
+        resource: &str,
+        region: &str,
+        service: &str,
+    ) -> Result<Vec<IntegrationScanEntry>, IntegrationError> {
+        match details.provider.as_str() {
+            "aws" => self.scan_aws(rest_cfg, &details.id, resource, region, service),
+            "azure" => self.scan_azure(rest_cfg, &details.id, resource),
+            provider => Err(IntegrationError::NotFound(format!(
+                "Scanning is not supported for '{provider}' integrations"
+            ))),
+        }
+    }
+
     ///==========================================
     /// Integration push
     ///==========================================
@@ -6,12 +6,18 @@ mod audit_log_errors;
 mod audit_log_summary;
 mod audit_logs;
 mod backup_error;
+mod backup_manifest;
 mod backups;
 mod crypto_algorithm;
+mod crypto_envelope;
+mod crypto_error;
 mod environment_details;
 mod environment_error;
 mod environment_tag;
 mod environments;
+mod grant_details;
+mod grant_error;
+mod grants;
 mod group_details;
 mod group_error;
 mod groups;
@@ -22,6 +28,7 @@ mod imports;
 mod integration_details;
 mod integration_error;
 mod integration_node;
+mod integration_scan;
 mod integrations;
 mod invitation_details;
 mod invitation_error;
@@ -30,6 +37,7 @@ mod openapi;
 mod parameter_details;
 mod parameter_error;
 mod parameter_export;
+mod parameter_history;
 mod parameter_rules;
 mod parameters;
 mod project_details;
@@ -59,11 +67,18 @@ pub use audit_log_errors::AuditLogError;
 pub use audit_log_summary::AuditLogSummary;
 pub use audit_logs::AuditLogs;
 pub use backup_error::BackupError;
+pub use backup_manifest::{backup_digest, BackupManifest, BackupManifestEntry};
 pub use backups::{BackupSnapshotDetails, Backups};
+pub use crypto_algorithm::CryptoAlgorithm;
+pub use crypto_envelope::{decrypt_envelopes, CryptoEnvelope, CryptoKdf};
+pub use crypto_error::CryptoError;
 pub use environment_details::EnvironmentDetails;
 pub use environment_error::EnvironmentError;
 pub use environment_tag::EnvironmentTag;
 pub use environments::{EnvironmentUrlMap, Environments};
+pub use grant_details::{role_rank, GrantDetails, GrantNameMap};
+pub use grant_error::GrantError;
+pub use grants::Grants;
 pub use group_details::GroupDetails;
 pub use group_error::GroupError;
 pub use groups::Groups;
@@ -74,17 +89,22 @@ pub use imports::Imports;
 pub use integration_details::IntegrationDetails;
 pub use integration_error::IntegrationError;
 pub use integration_node::IntegrationNode;
+pub use integration_scan::{scan_entries, IntegrationScanEntry};
 pub use integrations::Integrations;
 pub use invitation_details::InvitationDetails;
 pub use invitation_error::InvitationError;
 pub use invitations::Invitations;
 pub use openapi::{
-    auth_details, extract_details, extract_from_json, last_from_url, page_size, parent_id_from_url,
-    response_message, OpenApiConfig, NO_PAGE_COUNT, NO_PAGE_SIZE,
+    auth_details, extract_details, extract_from_json, last_from_url, object_url, page_size,
+    parent_id_from_url, response_message, OpenApiConfig, NO_PAGE_COUNT, NO_PAGE_SIZE,
 };
 pub use parameter_details::ParameterDetails;
 pub use parameter_error::ParameterError;
-pub use parameter_export::{ParamExportFormat, ParamExportOptions};
+pub use parameter_export::{
+    env_export_name, format_export_values, format_github_actions, resolve_base_types,
+    KubernetesExport, ParamExportFormat, ParamExportOptions,
+};
+pub use parameter_history::ParameterHistory;
 pub use parameter_rules::{ParamRuleType, ParameterRuleDetail};
 pub use parameters::{ParameterDetailMap, Parameters};
 pub use project_details::ProjectDetails;
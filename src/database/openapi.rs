@@ -83,6 +83,17 @@ pub fn last_from_url(url: &str) -> &str {
         .unwrap_or_default()
 }
 
+/// Builds the REST URL of an object from its identifier (e.g. `users` and `<id>` gives
+/// `.../api/v1/users/<id>/`).
+pub fn object_url(rest_cfg: &OpenApiConfig, kind: &str, id: &str) -> String {
+    format!(
+        "{}/api/v1/{}/{}/",
+        rest_cfg.base_path,
+        kind,
+        urlencoding::encode(id)
+    )
+}
+
 pub fn parent_id_from_url<'a>(url: &'a str, child: &'a str) -> &'a str {
     let parts: Vec<&str> = url.split(child).collect();
     last_from_url(parts[0])
@@ -159,6 +170,13 @@ mod tests {
             rest_success: vec!["abc".to_string(), "def".to_string()],
             rest_page_size: Some(2300),
             accept_invalid_certs: None,
+            encryption_key_file: None,
+            key_prefix: None,
+            key_strip_prefix: None,
+            key_uppercase: false,
+            key_sanitize: false,
+            offline_cache: false,
+            offline_cache_ttl: Duration::from_secs(0),
         };
         let openapi_cfg = OpenApiConfig::from(&ct_cfg);
         // check that the trailing slash removed from the URL
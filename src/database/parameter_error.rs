@@ -13,6 +13,9 @@ pub enum ParameterError {
     ResponseError(String),
     EvaluationError(String),
     TemplateEvalError(TemplateLookupError),
+    ExportKeyConflict(String),
+    InvalidExportKeys(String),
+    InvalidExportValues(String),
 }
 
 impl fmt::Display for ParameterError {
@@ -43,6 +46,15 @@ impl fmt::Display for ParameterError {
                     template_eval_errors(tle)
                 )
             }
+            ParameterError::ExportKeyConflict(msg) => {
+                write!(f, "Export key conflict: {msg}")
+            }
+            ParameterError::InvalidExportKeys(msg) => {
+                write!(f, "Invalid export keys: {msg}")
+            }
+            ParameterError::InvalidExportValues(msg) => {
+                write!(f, "Invalid export values: {msg}")
+            }
         }
     }
 }
@@ -1,10 +1,32 @@
+use crate::database::ParameterError;
+use rand_core::{OsRng, RngCore};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
 use std::str::FromStr;
 
+/// Delimiter between the levels of the nested formats, when none is given.
+pub const DEFAULT_NESTED_DELIMITER: &str = ".";
+
+/// Variable name for the hcl map, when none is given.
+pub const DEFAULT_HCL_MAP_NAME: &str = "cloudtruth";
+
 #[derive(Debug)]
 pub enum ParamExportFormat {
     Docker,
     Dotenv,
     Shell,
+    Json,
+    Yaml,
+    NestedJson,
+    NestedYaml,
+    Kubernetes,
+    Tfvars,
+    Properties,
+    Hcl,
+    GithubActions,
+    Gitlab,
+    AzurePipelines,
 }
 
 /// Converts to ParamExportFormat from a &str.
@@ -16,11 +38,43 @@ impl FromStr for ParamExportFormat {
             "docker" => Ok(ParamExportFormat::Docker),
             "dotenv" => Ok(ParamExportFormat::Dotenv),
             "shell" => Ok(ParamExportFormat::Shell),
+            "json" => Ok(ParamExportFormat::Json),
+            "yaml" => Ok(ParamExportFormat::Yaml),
+            "nested-json" => Ok(ParamExportFormat::NestedJson),
+            "nested-yaml" => Ok(ParamExportFormat::NestedYaml),
+            "kubernetes" => Ok(ParamExportFormat::Kubernetes),
+            "tfvars" => Ok(ParamExportFormat::Tfvars),
+            "properties" => Ok(ParamExportFormat::Properties),
+            "hcl" => Ok(ParamExportFormat::Hcl),
+            "github-actions" => Ok(ParamExportFormat::GithubActions),
+            "gitlab" => Ok(ParamExportFormat::Gitlab),
+            "azure-pipelines" => Ok(ParamExportFormat::AzurePipelines),
             _ => Err(()),
         }
     }
 }
 
+impl ParamExportFormat {
+    /// Formats built by the CLI from the parameter values, instead of by the server. The docker,
+    /// dotenv and shell formats are only built by the CLI when values need to be decrypted.
+    pub fn is_client_side(&self) -> bool {
+        !matches!(
+            self,
+            ParamExportFormat::Docker | ParamExportFormat::Dotenv | ParamExportFormat::Shell
+        )
+    }
+}
+
+/// The variable name for the docker, dotenv and shell formats: like the server, the parameter
+/// name is upper-cased, and names that are not alphanumeric or '_' are left out.
+pub fn env_export_name(name: &str) -> Option<String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some(name.to_uppercase())
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub struct ParamExportOptions {
     pub format: ParamExportFormat,
@@ -31,4 +85,840 @@ pub struct ParamExportOptions {
     pub secrets: Option<bool>,
     pub as_of: Option<String>,
     pub tag: Option<String>,
+    pub delimiter: Option<String>,
+    pub map_name: Option<String>,
+}
+
+impl ParamExportOptions {
+    /// Checks the parameter name against the `starts_with`, `ends_with` and `contains` filters.
+    /// Like the server, the docker, dotenv and shell filters ignore case.
+    pub fn includes(&self, name: &str) -> bool {
+        let fold = |s: &str| {
+            if self.format.is_client_side() {
+                s.to_string()
+            } else {
+                s.to_uppercase()
+            }
+        };
+        let name = fold(name);
+        self.starts_with
+            .as_deref()
+            .map_or(true, |s| name.starts_with(&fold(s)))
+            && self
+                .ends_with
+                .as_deref()
+                .map_or(true, |s| name.ends_with(&fold(s)))
+            && self
+                .contains
+                .as_deref()
+                .map_or(true, |s| name.contains(&fold(s)))
+    }
+}
+
+/// Escapes the data of a GitHub Actions workflow command.
+fn github_command_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Builds the GitHub Actions output: the `::add-mask::` commands for the secret values (one per
+/// line of the value), and the `$GITHUB_ENV` file content. Multi-line values use the heredoc
+/// syntax, with a random delimiter that is not in the value.
+pub fn format_github_actions(
+    values: &BTreeMap<String, String>,
+    secrets: &BTreeSet<String>,
+) -> (String, String) {
+    let mut commands = String::new();
+    let mut env_file = String::new();
+    for (name, value) in values {
+        if secrets.contains(name) {
+            for line in value.lines().filter(|l| !l.trim().is_empty()) {
+                let _ = writeln!(commands, "::add-mask::{}", github_command_data(line));
+            }
+        }
+        if value.contains('\n') || value.contains('\r') {
+            let delimiter = loop {
+                let mut random = [0u8; 16];
+                OsRng.fill_bytes(&mut random);
+                let delimiter = format!("ghadelimiter_{}", hex::encode(random));
+                if !value.contains(&delimiter) {
+                    break delimiter;
+                }
+            };
+            let _ = writeln!(env_file, "{name}<<{delimiter}\n{value}\n{delimiter}");
+        } else {
+            let _ = writeln!(env_file, "{name}={value}");
+        }
+    }
+    (commands, env_file)
+}
+
+/// Escapes an Azure Pipelines logging command value.
+fn azure_command_value(value: &str) -> String {
+    value
+        .replace('%', "%AZP25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes an Azure Pipelines logging command property, like the variable name.
+fn azure_command_property(value: &str) -> String {
+    azure_command_value(value)
+        .replace(';', "%3B")
+        .replace(']', "%5D")
+}
+
+/// Settings for the Kubernetes manifests: the object names, and where the values came from.
+#[derive(Debug, Default)]
+pub struct KubernetesExport {
+    pub name: String,
+    pub namespace: Option<String>,
+    // a single `List`, instead of separate documents
+    pub list: bool,
+    pub project: String,
+    pub environment: String,
+    pub tag: Option<String>,
+}
+
+/// Label values are limited to 63 alphanumeric, '-', '_' or '.' characters, and must start and
+/// end with an alphanumeric character.
+fn label_value(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let value: String = value
+        .trim_matches(|c: char| !c.is_ascii_alphanumeric())
+        .chars()
+        .take(63)
+        .collect();
+    value
+        .trim_end_matches(|c: char| !c.is_ascii_alphanumeric())
+        .to_string()
+}
+
+impl KubernetesExport {
+    fn metadata(&self) -> Value {
+        let mut labels = Map::new();
+        labels.insert(
+            "cloudtruth.com/project".to_string(),
+            Value::String(label_value(&self.project)),
+        );
+        labels.insert(
+            "cloudtruth.com/environment".to_string(),
+            Value::String(label_value(&self.environment)),
+        );
+        if let Some(tag) = &self.tag {
+            labels.insert(
+                "cloudtruth.com/tag".to_string(),
+                Value::String(label_value(tag)),
+            );
+        }
+        let mut metadata = Map::new();
+        metadata.insert("name".to_string(), Value::String(self.name.clone()));
+        if let Some(namespace) = &self.namespace {
+            metadata.insert("namespace".to_string(), Value::String(namespace.clone()));
+        }
+        metadata.insert("labels".to_string(), Value::Object(labels));
+        Value::Object(metadata)
+    }
+
+    /// Builds a `ConfigMap` with the plain values, and a `Secret` with the `secrets` values. An
+    /// empty `Secret` is left out.
+    pub fn format(
+        &self,
+        values: &BTreeMap<String, String>,
+        secrets: &BTreeSet<String>,
+    ) -> Result<String, ParameterError> {
+        let invalid: Vec<&str> = values
+            .keys()
+            .filter(|k| {
+                k.is_empty()
+                    || !k
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+            })
+            .map(String::as_str)
+            .collect();
+        if !invalid.is_empty() {
+            return Err(ParameterError::InvalidExportKeys(format!(
+                "Kubernetes keys may only contain alphanumeric, '-', '_' or '.' characters: {}",
+                invalid.join(", ")
+            )));
+        }
+        let (secret_values, plain_values): (BTreeMap<&String, &String>, _) =
+            values.iter().partition(|(k, _)| secrets.contains(*k));
+        let mut items = vec![json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": self.metadata(),
+            "data": plain_values,
+        })];
+        if !secret_values.is_empty() {
+            let data: BTreeMap<&String, String> = secret_values
+                .into_iter()
+                .map(|(k, v)| (k, base64::encode(v)))
+                .collect();
+            items.push(json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": self.metadata(),
+                "type": "Opaque",
+                "data": data,
+            }));
+        }
+
+        let to_yaml = |value: &Value| {
+            serde_yaml::to_string(value).map_err(|e| ParameterError::UnhandledError(e.to_string()))
+        };
+        if self.list {
+            to_yaml(&json!({
+                "apiVersion": "v1",
+                "kind": "List",
+                "items": items,
+            }))
+        } else {
+            let documents: Result<Vec<String>, ParameterError> =
+                items.iter().map(to_yaml).collect();
+            Ok(documents?.join(""))
+        }
+    }
+}
+
+/// Builds the nested object, where each delimited part of the key is another level.
+fn nest_values(
+    values: &BTreeMap<String, String>,
+    delimiter: &str,
+) -> Result<Map<String, Value>, ParameterError> {
+    let conflict = |key: &str| {
+        ParameterError::ExportKeyConflict(format!(
+            "'{key}' is both a value and a parent of other values (delimiter '{delimiter}')"
+        ))
+    };
+    let mut result = Map::new();
+    for (key, value) in values {
+        let mut parts: Vec<&str> = key.split(delimiter).collect();
+        let last = parts.pop().unwrap();
+        let mut level = &mut result;
+        for part in parts {
+            let entry = level
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            level = match entry {
+                Value::Object(map) => map,
+                _ => return Err(conflict(key)),
+            };
+        }
+        if level.contains_key(last) {
+            return Err(conflict(key));
+        }
+        level.insert(last.to_string(), Value::String(value.clone()));
+    }
+    Ok(result)
+}
+
+/// Quotes the value as an HCL string, including the escapes for template sequences.
+fn hcl_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '$' | '%' if chars.peek() == Some(&'{') => {
+                result.push(c);
+                result.push(c);
+            }
+            c if c.is_control() => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Replaces custom type names with the base type they derive from (e.g. `integer`), using the
+/// `parents` map of type names to their parent type name.
+pub fn resolve_base_types(types: &mut BTreeMap<String, String>, parents: &HashMap<String, String>) {
+    for param_type in types.values_mut() {
+        // the depth limit guards against a cycle
+        for _ in 0..parents.len() {
+            match parents.get(param_type.as_str()).filter(|p| !p.is_empty()) {
+                Some(parent) => *param_type = parent.clone(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Integer and boolean values are left unquoted, when the value matches the (base) type.
+fn tfvars_value(value: &str, param_type: Option<&String>) -> String {
+    let typed = match param_type.map(String::as_str) {
+        Some("integer") => value.parse::<i64>().is_ok(),
+        Some("boolean") => value == "true" || value == "false",
+        _ => false,
+    };
+    if typed {
+        value.to_string()
+    } else {
+        hcl_string(value)
+    }
+}
+
+fn is_hcl_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Double-quotes the dotenv value, escaping the characters dotenv parsers unescape.
+fn dotenv_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\"{escaped}\"")
+}
+
+/// Single-quotes the shell value, unless it is a plain word.
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c))
+    {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Escapes the text like `java.util.Properties.store()`, so it reads back the same.
+fn properties_escape(text: &str, is_key: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (index, c) in text.chars().enumerate() {
+        match c {
+            ' ' if is_key || index == 0 => result.push_str("\\ "),
+            '\\' => result.push_str("\\\\"),
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\x0c' => result.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                result.push('\\');
+                result.push(c);
+            }
+            c if (' '..='~').contains(&c) => result.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let _ = write!(result, "\\u{unit:04X}");
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Formats the (already filtered) parameter values for the client-side formats. The `types` are
+/// the parameter type names, used for the typed formats, and the `secrets` are the names of the
+/// values the CI formats mask.
+pub fn format_export_values(
+    values: &BTreeMap<String, String>,
+    types: &BTreeMap<String, String>,
+    secrets: &BTreeSet<String>,
+    options: &ParamExportOptions,
+) -> Result<String, ParameterError> {
+    let delimiter = options
+        .delimiter
+        .as_deref()
+        .filter(|d| !d.is_empty())
+        .unwrap_or(DEFAULT_NESTED_DELIMITER);
+    let flat = || -> Map<String, Value> {
+        values
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect()
+    };
+    let output = match options.format {
+        ParamExportFormat::Json => serde_json::to_string_pretty(&flat()),
+        ParamExportFormat::NestedJson => {
+            serde_json::to_string_pretty(&nest_values(values, delimiter)?)
+        }
+        ParamExportFormat::Yaml => {
+            return serde_yaml::to_string(&flat())
+                .map_err(|e| ParameterError::UnhandledError(e.to_string()))
+        }
+        ParamExportFormat::NestedYaml => {
+            return serde_yaml::to_string(&nest_values(values, delimiter)?)
+                .map_err(|e| ParameterError::UnhandledError(e.to_string()))
+        }
+        ParamExportFormat::Tfvars => {
+            let invalid: Vec<&str> = values
+                .keys()
+                .filter(|k| !is_hcl_identifier(k))
+                .map(String::as_str)
+                .collect();
+            if !invalid.is_empty() {
+                return Err(ParameterError::InvalidExportKeys(format!(
+                    "Terraform variable names may only contain letters, digits, '_' or '-': {}",
+                    invalid.join(", ")
+                )));
+            }
+            let mut result = String::new();
+            for (k, v) in values {
+                let _ = writeln!(result, "{} = {}", k, tfvars_value(v, types.get(k)));
+            }
+            return Ok(result);
+        }
+        ParamExportFormat::Properties => {
+            let mut result = String::new();
+            for (k, v) in values {
+                let _ = writeln!(
+                    result,
+                    "{}={}",
+                    properties_escape(k, true),
+                    properties_escape(v, false)
+                );
+            }
+            return Ok(result);
+        }
+        ParamExportFormat::Hcl => {
+            let name = options.map_name.as_deref().unwrap_or(DEFAULT_HCL_MAP_NAME);
+            if !is_hcl_identifier(name) {
+                return Err(ParameterError::InvalidExportKeys(format!(
+                    "'{name}' is not a valid HCL variable name"
+                )));
+            }
+            let mut entries = String::new();
+            for (k, v) in values {
+                let _ = writeln!(entries, "  {} = {}", hcl_string(k), hcl_string(v));
+            }
+            return Ok(format!("{name} = {{\n{entries}}}\n"));
+        }
+        ParamExportFormat::GithubActions => {
+            let (commands, env_file) = format_github_actions(values, secrets);
+            return Ok(commands + &env_file);
+        }
+        ParamExportFormat::Gitlab => {
+            let invalid: Vec<&str> = values
+                .iter()
+                .filter(|(k, v)| {
+                    k.is_empty()
+                        || !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        || v.contains('\n')
+                        || v.contains('\r')
+                })
+                .map(|(k, _)| k.as_str())
+                .collect();
+            if !invalid.is_empty() {
+                return Err(ParameterError::InvalidExportKeys(format!(
+                    "GitLab dotenv variables need alphanumeric or '_' names, and single-line values: {}",
+                    invalid.join(", ")
+                )));
+            }
+            let mut result = String::new();
+            for (k, v) in values {
+                let _ = writeln!(result, "{k}={v}");
+            }
+            return Ok(result);
+        }
+        ParamExportFormat::AzurePipelines => {
+            let mut result = String::new();
+            for (k, v) in values {
+                let secret = if secrets.contains(k) {
+                    ";issecret=true"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    result,
+                    "##vso[task.setvariable variable={}{}]{}",
+                    azure_command_property(k),
+                    secret,
+                    azure_command_value(v)
+                );
+            }
+            return Ok(result);
+        }
+        ParamExportFormat::Kubernetes => {
+            return Err(ParameterError::UnhandledError(
+                "The Kubernetes format is built by KubernetesExport".to_string(),
+            ))
+        }
+        ParamExportFormat::Docker => {
+            // docker environment files have no quoting, so values must be a single line
+            let invalid: Vec<&str> = values
+                .iter()
+                .filter(|(_, v)| v.contains('\n') || v.contains('\r'))
+                .map(|(k, _)| k.as_str())
+                .collect();
+            if !invalid.is_empty() {
+                return Err(ParameterError::InvalidExportValues(format!(
+                    "Docker environment files cannot have multi-line values: {}",
+                    invalid.join(", ")
+                )));
+            }
+            let mut result = String::new();
+            for (k, v) in values {
+                let _ = writeln!(result, "{k}={v}");
+            }
+            return Ok(result);
+        }
+        ParamExportFormat::Dotenv | ParamExportFormat::Shell => {
+            let prefix = if options.export.unwrap_or(false) {
+                "export "
+            } else {
+                ""
+            };
+            let mut result = String::new();
+            for (k, v) in values {
+                let value = match options.format {
+                    ParamExportFormat::Dotenv => dotenv_quote(v),
+                    _ => shell_quote(v),
+                };
+                let _ = writeln!(result, "{prefix}{k}={value}");
+            }
+            return Ok(result);
+        }
+    };
+    output.map_err(|e| ParameterError::UnhandledError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: ParamExportFormat, delimiter: Option<&str>) -> ParamExportOptions {
+        ParamExportOptions {
+            format,
+            starts_with: None,
+            ends_with: None,
+            contains: None,
+            export: None,
+            secrets: None,
+            as_of: None,
+            tag: None,
+            delimiter: delimiter.map(String::from),
+            map_name: None,
+        }
+    }
+
+    #[test]
+    fn export_structured_formats() {
+        let values = BTreeMap::from([
+            ("APP__DB__HOST".to_string(), "localhost".to_string()),
+            ("APP__DB__PORT".to_string(), "5432".to_string()),
+            ("APP__NAME".to_string(), "it's \"quoted\"".to_string()),
+        ]);
+        let json = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &BTreeSet::new(),
+            &options(ParamExportFormat::Json, None),
+        )
+        .unwrap();
+        let parsed: BTreeMap<String, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, values);
+
+        let nested = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &BTreeSet::new(),
+            &options(ParamExportFormat::NestedJson, Some("__")),
+        )
+        .unwrap();
+        let parsed: Value = serde_json::from_str(&nested).unwrap();
+        assert_eq!(parsed["APP"]["DB"]["PORT"], "5432");
+        assert_eq!(parsed["APP"]["NAME"], "it's \"quoted\"");
+
+        let yaml = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &BTreeSet::new(),
+            &options(ParamExportFormat::NestedYaml, Some("__")),
+        )
+        .unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed["APP"]["DB"]["HOST"], "localhost");
+
+        // the default delimiter leaves these keys flat
+        let nested = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &BTreeSet::new(),
+            &options(ParamExportFormat::NestedJson, None),
+        )
+        .unwrap();
+        let parsed: BTreeMap<String, String> = serde_json::from_str(&nested).unwrap();
+        assert_eq!(parsed, values);
+    }
+
+    #[test]
+    fn export_terraform_and_properties() {
+        let values = BTreeMap::from([
+            ("count".to_string(), "3".to_string()),
+            ("enabled".to_string(), "yes".to_string()),
+            ("message".to_string(), "say \"hi\"\n${var}".to_string()),
+            ("url".to_string(), "http://host:80/a=b café".to_string()),
+        ]);
+        let types = BTreeMap::from([
+            ("count".to_string(), "integer".to_string()),
+            ("enabled".to_string(), "boolean".to_string()),
+        ]);
+        // custom types are exported like the type they derive from
+        let mut types = types;
+        types.insert("port".to_string(), "port-number".to_string());
+        types.insert("url".to_string(), "uri".to_string());
+        let parents = HashMap::from([
+            ("port-number".to_string(), "positive".to_string()),
+            ("positive".to_string(), "integer".to_string()),
+            ("uri".to_string(), "string".to_string()),
+            ("integer".to_string(), "".to_string()),
+        ]);
+        resolve_base_types(&mut types, &parents);
+        assert_eq!(types["port"], "integer");
+        assert_eq!(types["url"], "string");
+        let mut values = values;
+        values.insert("port".to_string(), "8080".to_string());
+        let tfvars = format_export_values(
+            &values,
+            &types,
+            &BTreeSet::new(),
+            &options(ParamExportFormat::Tfvars, None),
+        )
+        .unwrap();
+        assert_eq!(
+            tfvars,
+            concat!(
+                "count = 3\n",
+                "enabled = \"yes\"\n",
+                "message = \"say \\\"hi\\\"\\n$${var}\"\n",
+                "port = 8080\n",
+                "url = \"http://host:80/a=b café\"\n",
+            )
+        );
+
+        let properties = format_export_values(
+            &values,
+            &types,
+            &BTreeSet::new(),
+            &options(ParamExportFormat::Properties, None),
+        )
+        .unwrap();
+        assert!(properties.contains("message=say \"hi\"\\n${var}\n"));
+        assert!(properties.contains("url=http\\://host\\:80/a\\=b caf\\u00E9\n"));
+
+        let hcl = format_export_values(
+            &values,
+            &types,
+            &BTreeSet::new(),
+            &options(ParamExportFormat::Hcl, None),
+        )
+        .unwrap();
+        assert!(hcl.starts_with("cloudtruth = {\n  \"count\" = \"3\"\n"));
+        assert!(hcl.ends_with("}\n"));
+
+        let values = BTreeMap::from([("db.host".to_string(), "x".to_string())]);
+        assert!(format_export_values(
+            &values,
+            &types,
+            &BTreeSet::new(),
+            &options(ParamExportFormat::Tfvars, None)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn export_env_formats() {
+        assert_eq!(env_export_name("db_host"), Some("DB_HOST".to_string()));
+        assert_eq!(env_export_name("non.posix.key"), None);
+        let mut filtered = options(ParamExportFormat::Docker, None);
+        filtered.starts_with = Some("second".to_string());
+        assert!(filtered.includes("SECOND_PARAM"));
+        assert!(!filtered.includes("FIRST_PARAM"));
+
+        let values = BTreeMap::from([
+            ("PLAIN".to_string(), "posix_value".to_string()),
+            (
+                "QUOTED".to_string(),
+                "it's a \"value\" \\ $HOME".to_string(),
+            ),
+            ("MULTI".to_string(), "line 1\nline 2".to_string()),
+        ]);
+        let mut dotenv = options(ParamExportFormat::Dotenv, None);
+        dotenv.export = Some(true);
+        assert_eq!(
+            format_export_values(&values, &BTreeMap::new(), &BTreeSet::new(), &dotenv).unwrap(),
+            concat!(
+                "export MULTI=\"line 1\\nline 2\"\n",
+                "export PLAIN=\"posix_value\"\n",
+                "export QUOTED=\"it's a \\\"value\\\" \\\\ $HOME\"\n",
+            )
+        );
+        assert_eq!(
+            format_export_values(
+                &values,
+                &BTreeMap::new(),
+                &BTreeSet::new(),
+                &options(ParamExportFormat::Shell, None)
+            )
+            .unwrap(),
+            concat!(
+                "MULTI='line 1\nline 2'\n",
+                "PLAIN=posix_value\n",
+                "QUOTED='it'\\''s a \"value\" \\ $HOME'\n",
+            )
+        );
+
+        // docker environment files cannot have a newline in the value
+        let docker = options(ParamExportFormat::Docker, None);
+        assert!(matches!(
+            format_export_values(&values, &BTreeMap::new(), &BTreeSet::new(), &docker),
+            Err(ParameterError::InvalidExportValues(_))
+        ));
+        let single = BTreeMap::from([("PLAIN".to_string(), "a value with spaces".to_string())]);
+        assert_eq!(
+            format_export_values(&single, &BTreeMap::new(), &BTreeSet::new(), &docker).unwrap(),
+            "PLAIN=a value with spaces\n"
+        );
+    }
+
+    #[test]
+    fn export_ci_formats() {
+        let values = BTreeMap::from([
+            ("CERT".to_string(), "line 1\nline 2".to_string()),
+            ("HOST".to_string(), "example.com".to_string()),
+            ("TOKEN".to_string(), "100%;secret]".to_string()),
+        ]);
+        let secrets = BTreeSet::from(["CERT".to_string(), "TOKEN".to_string()]);
+        let (commands, env_file) = format_github_actions(&values, &secrets);
+        assert_eq!(
+            commands,
+            "::add-mask::line 1\n::add-mask::line 2\n::add-mask::100%25;secret]\n"
+        );
+        let (first, rest) = env_file.split_once('\n').unwrap();
+        let delimiter = first.strip_prefix("CERT<<ghadelimiter_").unwrap();
+        assert_eq!(
+            rest,
+            format!(
+                "line 1\nline 2\nghadelimiter_{delimiter}\nHOST=example.com\nTOKEN=100%;secret]\n"
+            )
+        );
+
+        let azure = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &secrets,
+            &options(ParamExportFormat::AzurePipelines, None),
+        )
+        .unwrap();
+        assert_eq!(
+            azure,
+            concat!(
+                "##vso[task.setvariable variable=CERT;issecret=true]line 1%0Aline 2\n",
+                "##vso[task.setvariable variable=HOST]example.com\n",
+                "##vso[task.setvariable variable=TOKEN;issecret=true]100%AZP25;secret]\n",
+            )
+        );
+
+        let gitlab = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &secrets,
+            &options(ParamExportFormat::Gitlab, None),
+        );
+        assert!(gitlab.unwrap_err().to_string().contains("CERT"));
+    }
+
+    #[test]
+    fn export_kubernetes() {
+        let values = BTreeMap::from([
+            ("DB_HOST".to_string(), "localhost".to_string()),
+            ("DB_PASSWORD".to_string(), "hunter22".to_string()),
+        ]);
+        let secrets = BTreeSet::from(["DB_PASSWORD".to_string()]);
+        let export = KubernetesExport {
+            name: "my-app".to_string(),
+            namespace: Some("prod".to_string()),
+            project: "My Project".to_string(),
+            environment: "production".to_string(),
+            ..Default::default()
+        };
+        let output = export.format(&values, &secrets).unwrap();
+        assert!(!output.contains("hunter22"));
+        let documents: Vec<Value> = output
+            .split("---\n")
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| serde_yaml::from_str(d).unwrap())
+            .collect();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["kind"], "ConfigMap");
+        assert_eq!(documents[0]["data"]["DB_HOST"], "localhost");
+        assert!(documents[0]["data"].get("DB_PASSWORD").is_none());
+        assert_eq!(
+            documents[0]["metadata"]["labels"]["cloudtruth.com/project"],
+            "My-Project"
+        );
+        assert_eq!(documents[1]["kind"], "Secret");
+        assert_eq!(documents[1]["metadata"]["namespace"], "prod");
+        assert_eq!(
+            documents[1]["data"]["DB_PASSWORD"],
+            base64::encode("hunter22")
+        );
+
+        let export = KubernetesExport {
+            list: true,
+            tag: Some("v1.2".to_string()),
+            ..export
+        };
+        let list: Value =
+            serde_yaml::from_str(&export.format(&values, &BTreeSet::new()).unwrap()).unwrap();
+        assert_eq!(list["kind"], "List");
+        assert_eq!(list["items"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            list["items"][0]["metadata"]["labels"]["cloudtruth.com/tag"],
+            "v1.2"
+        );
+
+        let values = BTreeMap::from([("db/host".to_string(), "x".to_string())]);
+        assert!(export.format(&values, &secrets).is_err());
+    }
+
+    #[test]
+    fn export_nested_conflicts() {
+        let values = BTreeMap::from([
+            ("a.b".to_string(), "1".to_string()),
+            ("a.b.c".to_string(), "2".to_string()),
+        ]);
+        let result = format_export_values(
+            &values,
+            &BTreeMap::new(),
+            &BTreeSet::new(),
+            &options(ParamExportFormat::NestedYaml, None),
+        );
+        assert!(result.is_err());
+
+        let mut filters = options(ParamExportFormat::Json, None);
+        filters.starts_with = Some("a.".to_string());
+        filters.contains = Some("c".to_string());
+        assert!(filters.includes("a.b.c"));
+        assert!(!filters.includes("a.b"));
+    }
 }
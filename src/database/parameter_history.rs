@@ -0,0 +1,71 @@
+use crate::database::HistoryAction;
+use cloudtruth_restapi::models::{HistoryModelEnum, ParameterTimelineEntry};
+
+#[derive(Debug, Clone)]
+pub struct ParameterHistory {
+    pub id: String,
+    pub name: String,
+    pub change_type: HistoryAction,
+    pub model: String,
+    /// Environments where the change was made directly.
+    pub environments: Vec<String>,
+    /// Identifiers of the `environments`, in the same order.
+    pub environment_ids: Vec<String>,
+    /// Environments that inherited the change from a parent.
+    pub inherited: Vec<String>,
+    pub modified_at: String,
+    pub user_name: String,
+}
+
+impl From<&ParameterTimelineEntry> for ParameterHistory {
+    fn from(api: &ParameterTimelineEntry) -> Self {
+        let api_param = api.history_parameter.clone().unwrap_or_default();
+        let model = match api.history_model.as_deref() {
+            Some(HistoryModelEnum::Parameter) => "parameter",
+            Some(HistoryModelEnum::ParameterRule) => "rule",
+            Some(HistoryModelEnum::Value) => "value",
+            _ => "unknown",
+        };
+        ParameterHistory {
+            id: api_param.parameter_id.clone().unwrap_or_default(),
+            name: api_param.name.clone(),
+            change_type: HistoryAction::from(*api.history_type.clone().unwrap_or_default()),
+            model: model.to_string(),
+            environments: api
+                .history_environments
+                .iter()
+                .filter(|e| e._override)
+                .map(|e| e.name.clone())
+                .collect(),
+            environment_ids: api
+                .history_environments
+                .iter()
+                .filter(|e| e._override)
+                .map(|e| e.environment_id.clone().unwrap_or_default())
+                .collect(),
+            inherited: api
+                .history_environments
+                .iter()
+                .filter(|e| !e._override)
+                .map(|e| e.name.clone())
+                .collect(),
+            modified_at: api.modified_at.clone().unwrap_or_default(),
+            user_name: api.modified_by.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl ParameterHistory {
+    pub fn get_property(&self, name: &str) -> String {
+        match name {
+            "name" => self.name.clone(),
+            "action" => self.change_type.to_string(),
+            "model" => self.model.clone(),
+            "environments" => self.environments.join(", "),
+            "inherited" => self.inherited.join(", "),
+            "modified_at" => self.modified_at.clone(),
+            "user_name" => self.user_name.clone(),
+            x => format!("Unhandled property: {x}"),
+        }
+    }
+}
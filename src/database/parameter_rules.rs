@@ -1,6 +1,7 @@
 use cloudtruth_restapi::models::{ParameterRule, ParameterRuleTypeEnum, ParameterTypeRule};
 use std::fmt;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ParamRuleType {
@@ -60,6 +61,22 @@ impl fmt::Display for ParamRuleType {
     }
 }
 
+/// Accepts both the server names (e.g. "max_len") and the CLI names (e.g. "max-len").
+impl FromStr for ParamRuleType {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ParamRuleType, Self::Err> {
+        match input {
+            "max" => Ok(Self::Max),
+            "min" => Ok(Self::Min),
+            "max_len" | "max-len" => Ok(Self::MaxLen),
+            "min_len" | "min-len" => Ok(Self::MinLen),
+            "regex" => Ok(Self::Regex),
+            _ => Err(()),
+        }
+    }
+}
+
 impl From<&ParameterRule> for ParameterRuleDetail {
     fn from(api: &ParameterRule) -> Self {
         Self {
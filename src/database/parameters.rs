@@ -1,7 +1,7 @@
 use crate::database::{
     extract_details, extract_from_json, page_size, response_message, OpenApiConfig,
-    ParamExportOptions, ParamRuleType, ParameterDetails, ParameterError, TaskStepDetails,
-    NO_PAGE_COUNT, NO_PAGE_SIZE,
+    ParamExportOptions, ParamRuleType, ParameterDetails, ParameterError, ParameterHistory,
+    TaskStepDetails, NO_PAGE_COUNT, NO_PAGE_SIZE,
 };
 use cloudtruth_restapi::apis::projects_api::*;
 use cloudtruth_restapi::apis::utils_api::utils_generate_password_create;
@@ -10,6 +10,7 @@ use cloudtruth_restapi::models::{
     ParameterCopy, ParameterCreate, ParameterRuleCreate, ParameterRuleTypeEnum,
     PatchedParameterRuleUpdate, PatchedParameterUpdate, PatchedValueUpdate, ValueCreate,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::result::Result;
 
@@ -26,9 +27,13 @@ const NO_NAME_STARTS: Option<&str> = None;
 
 pub struct Parameters {}
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ParameterValueEntry {
     pub value: String,
     pub error: String,
+    pub secret: bool,
+    #[serde(default)]
+    pub param_type: String,
 }
 
 pub type ParameterDetailMap = HashMap<String, ParameterDetails>;
@@ -198,6 +203,29 @@ impl Parameters {
         }
     }
 
+    /// Fetches the parameter values for the client-side export formats, limited by the `options`
+    /// filters. Secret values are masked unless `options.secrets` is set.
+    pub fn export_values(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        proj_id: &str,
+        env_id: &str,
+        options: &ParamExportOptions,
+    ) -> Result<ParameterValueMap, ParameterError> {
+        let mut values = self.get_parameter_values(
+            rest_cfg,
+            proj_id,
+            env_id,
+            !options.secrets.unwrap_or(false),
+            false,
+            true,
+            options.as_of.clone(),
+            options.tag.clone(),
+        )?;
+        values.retain(|k, _| options.includes(k));
+        Ok(values)
+    }
+
     /// Fetches the `ParameterDetails` for the specified project/environment/key_name.
     ///
     /// It will return `None` if the parameter does not exist. Other errors will be returned
@@ -297,6 +325,8 @@ impl Parameters {
             let entry = ParameterValueEntry {
                 value: param.value,
                 error: param.error,
+                secret: param.secret,
+                param_type: param.param_type,
             };
             env_vars.insert(param.key, entry);
         }
@@ -410,10 +440,37 @@ impl Parameters {
         mask_secrets: bool,
         as_of: Option<String>,
     ) -> Result<ParameterDetailMap, ParameterError> {
-        let mut result = ParameterDetailMap::new();
+        let details = self.get_environment_details(
+            rest_cfg,
+            proj_id,
+            Some(param_name),
+            immediate_parameters,
+            mask_secrets,
+            true,
+            as_of,
+        )?;
+        Ok(details
+            .into_iter()
+            .map(|d| (d.env_url.clone(), d))
+            .collect())
+    }
+
+    /// Gets the `ParameterDetails` for every environment value of the parameters (or only the
+    /// named parameter), in a single listing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_environment_details(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        proj_id: &str,
+        param_name: Option<&str>,
+        immediate_parameters: bool,
+        mask_secrets: bool,
+        evaluate: bool,
+        as_of: Option<String>,
+    ) -> Result<Vec<ParameterDetails>, ParameterError> {
+        let mut result: Vec<ParameterDetails> = Vec::new();
         let mut page_count = 1;
         loop {
-            let eval_arg = VALUES_TRUE;
             let response = projects_parameters_list(
                 rest_cfg,
                 proj_id,
@@ -421,10 +478,10 @@ impl Parameters {
                 NO_DESC_ICONTAINS,
                 None,
                 None, // cannot give an environment, or it will only get for that environment
-                eval_arg,
+                Some(evaluate),
                 immediate_parameters_arg(immediate_parameters),
                 mask_secrets_arg(mask_secrets),
-                Some(param_name),
+                param_name,
                 NO_NAME_CONTAINS,
                 NO_NAME_ICONTAINS,
                 NO_NAME_IEXACT,
@@ -445,7 +502,7 @@ impl Parameters {
                             for (_, api_value) in api_param.values {
                                 if let Some(value) = api_value {
                                     details.set_value(&value);
-                                    result.insert(details.env_url.clone(), details.clone());
+                                    result.push(details.clone());
                                 }
                             }
                         }
@@ -802,4 +859,48 @@ impl Parameters {
             Err(e) => Err(ParameterError::UnhandledError(e.to_string())),
         }
     }
+
+    /// Gets the history of all the parameters in the project.
+    pub fn get_histories(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        proj_id: &str,
+        as_of: Option<String>,
+        tag: Option<String>,
+    ) -> Result<Vec<ParameterHistory>, ParameterError> {
+        let response =
+            projects_parameters_timelines_retrieve(rest_cfg, proj_id, as_of, tag.as_deref());
+        match response {
+            Ok(data) => Ok(data.results.iter().map(ParameterHistory::from).collect()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(ParameterError::UnhandledError(e.to_string())),
+        }
+    }
+
+    /// Gets the history for a single parameter in the project.
+    pub fn get_history_for(
+        &self,
+        rest_cfg: &OpenApiConfig,
+        proj_id: &str,
+        param_id: &str,
+        as_of: Option<String>,
+        tag: Option<String>,
+    ) -> Result<Vec<ParameterHistory>, ParameterError> {
+        let response = projects_parameters_timeline_retrieve(
+            rest_cfg,
+            param_id,
+            proj_id,
+            as_of,
+            tag.as_deref(),
+        );
+        match response {
+            Ok(data) => Ok(data.results.iter().map(ParameterHistory::from).collect()),
+            Err(ResponseError(ref content)) => {
+                Err(response_error(&content.status, &content.content))
+            }
+            Err(e) => Err(ParameterError::UnhandledError(e.to_string())),
+        }
+    }
 }
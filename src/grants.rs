@@ -0,0 +1,280 @@
+use crate::cli::{
+    CONFIRM_FLAG, DELETE_SUBCMD, FORMAT_OPT, LIST_SUBCMD, ROLE_ARG, SET_SUBCMD, SHOW_TIMES_FLAG,
+};
+use crate::database::{
+    object_url, role_rank, Environments, GrantDetails, GrantNameMap, Grants, Groups, OpenApiConfig,
+    Projects, Users,
+};
+use crate::table::Table;
+use crate::utils::{
+    error_message, user_confirm, warn_missing_subcommand, warning_message, DEL_CONFIRM,
+};
+use clap::ArgMatches;
+use color_eyre::eyre::Result;
+use std::collections::BTreeMap;
+use std::process;
+
+const GRANT_USER_OPT: &str = "user";
+const GRANT_GROUP_OPT: &str = "group";
+const GRANT_PROJECT_OPT: &str = "project";
+const GRANT_ENV_OPT: &str = "environment";
+
+/// A user/group or project/environment that has been resolved to its URL.
+struct GrantTarget {
+    kind: &'static str,
+    name: String,
+    url: String,
+}
+
+impl GrantTarget {
+    fn display(&self) -> String {
+        format!("{} '{}'", self.kind, self.name)
+    }
+}
+
+/// Resolves the `--user` or `--group` argument to the principal URL.
+fn resolve_principal(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+) -> Result<Option<GrantTarget>> {
+    if let Some(user_name) = subcmd_args.value_of(GRANT_USER_OPT) {
+        match Users::new().get_id(rest_cfg, user_name)? {
+            Some(user_id) => Ok(Some(GrantTarget {
+                kind: "user",
+                name: user_name.to_string(),
+                url: object_url(rest_cfg, "users", &user_id),
+            })),
+            None => {
+                error_message(format!("User '{user_name}' not found"));
+                process::exit(70);
+            }
+        }
+    } else if let Some(group_name) = subcmd_args.value_of(GRANT_GROUP_OPT) {
+        match Groups::new().get_id(rest_cfg, group_name)? {
+            Some(group_id) => Ok(Some(GrantTarget {
+                kind: "group",
+                name: group_name.to_string(),
+                url: object_url(rest_cfg, "groups", &group_id),
+            })),
+            None => {
+                error_message(format!("Group '{group_name}' not found"));
+                process::exit(70);
+            }
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Resolves the `--project` or `--env` argument to the scope URL.
+fn resolve_scope(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+) -> Result<Option<GrantTarget>> {
+    if let Some(proj_name) = subcmd_args.value_of(GRANT_PROJECT_OPT) {
+        match Projects::new().get_details_by_name(rest_cfg, proj_name, false)? {
+            Some(details) => Ok(Some(GrantTarget {
+                kind: "project",
+                name: proj_name.to_string(),
+                url: details.url,
+            })),
+            None => {
+                error_message(format!("Project '{proj_name}' not found"));
+                process::exit(71);
+            }
+        }
+    } else if let Some(env_name) = subcmd_args.value_of(GRANT_ENV_OPT) {
+        match Environments::new().get_details_by_name(rest_cfg, env_name)? {
+            Some(details) => Ok(Some(GrantTarget {
+                kind: "environment",
+                name: env_name.to_string(),
+                url: details.url,
+            })),
+            None => {
+                error_message(format!("Environment '{env_name}' not found"));
+                process::exit(71);
+            }
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Gets the URL-to-name map for all the possible principals and scopes.
+fn grant_name_map(rest_cfg: &OpenApiConfig) -> Result<GrantNameMap> {
+    let mut name_map = GrantNameMap::new();
+    name_map.extend(Users::new().get_user_url_to_name_map(rest_cfg)?);
+    for group in Groups::new().get_groups_list(rest_cfg)? {
+        name_map.insert(group.url, group.name);
+    }
+    name_map.extend(Projects::new().get_url_name_map(rest_cfg));
+    name_map.extend(Environments::new().get_url_name_map(rest_cfg));
+    Ok(name_map)
+}
+
+/// Reduces the grants to the most permissive role for each scope.
+fn effective_grants(grants: Vec<GrantDetails>) -> Vec<GrantDetails> {
+    let mut by_scope: BTreeMap<(String, String), GrantDetails> = BTreeMap::new();
+    for grant in grants {
+        let key = (grant.scope_type.clone(), grant.scope_name.clone());
+        match by_scope.get(&key) {
+            Some(current) if role_rank(&current.role) >= role_rank(&grant.role) => {}
+            _ => {
+                by_scope.insert(key, grant);
+            }
+        }
+    }
+    by_scope.into_values().collect()
+}
+
+fn proc_grants_delete(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    grants: &Grants,
+) -> Result<()> {
+    let principal = resolve_principal(subcmd_args, rest_cfg)?.unwrap();
+    let scope = resolve_scope(subcmd_args, rest_cfg)?.unwrap();
+    let grant_id = grants.get_id(rest_cfg, &principal.url, &scope.url)?;
+
+    if let Some(grant_id) = grant_id {
+        let mut confirmed = subcmd_args.is_present(CONFIRM_FLAG);
+        if !confirmed {
+            confirmed = user_confirm(
+                format!(
+                    "Delete grant for {} on {}",
+                    principal.display(),
+                    scope.display()
+                ),
+                DEL_CONFIRM,
+            );
+        }
+
+        if !confirmed {
+            warning_message(format!(
+                "Grant for {} on {} not deleted!",
+                principal.display(),
+                scope.display()
+            ));
+        } else {
+            grants.delete_grant(rest_cfg, &grant_id)?;
+            println!(
+                "Deleted grant for {} on {}",
+                principal.display(),
+                scope.display()
+            );
+        }
+    } else {
+        warning_message(format!(
+            "No grant for {} on {}",
+            principal.display(),
+            scope.display()
+        ));
+    }
+    Ok(())
+}
+
+fn proc_grants_list(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    grants: &Grants,
+) -> Result<()> {
+    let show_times = subcmd_args.is_present(SHOW_TIMES_FLAG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let role = subcmd_args.value_of(ROLE_ARG);
+    let principal = resolve_principal(subcmd_args, rest_cfg)?;
+    let scope = resolve_scope(subcmd_args, rest_cfg)?;
+    let principal_url = principal.as_ref().map(|p| p.url.as_str());
+    let scope_url = scope.as_ref().map(|s| s.url.as_str());
+    let name_map = grant_name_map(rest_cfg)?;
+
+    let mut details =
+        grants.get_grant_details(rest_cfg, principal_url, scope_url, role, &name_map)?;
+    let for_user = matches!(&principal, Some(p) if p.kind == "user");
+    if let Some(user) = principal.as_ref().filter(|_| for_user) {
+        // the server only reports direct grants, so add the ones received through groups
+        for group in Groups::new().get_group_details_list(rest_cfg)? {
+            if !group.users.contains(&user.name) {
+                continue;
+            }
+            let mut group_grants =
+                grants.get_grant_details(rest_cfg, Some(&group.url), scope_url, role, &name_map)?;
+            for grant in group_grants.iter_mut() {
+                grant.principal_name = user.name.clone();
+                grant.principal_type = user.kind.to_string();
+                grant.source = format!("group: {}", group.name);
+            }
+            details.append(&mut group_grants);
+        }
+        details = effective_grants(details);
+    } else {
+        details.sort_by(|l, r| {
+            (&l.principal_name, &l.scope_name).cmp(&(&r.principal_name, &r.scope_name))
+        });
+    }
+
+    if details.is_empty() {
+        println!("No grants found");
+    } else {
+        let mut hdr = vec!["Principal", "Principal Type", "Scope", "Scope Type", "Role"];
+        let mut properties = vec!["principal", "principal-type", "scope", "scope-type", "role"];
+        if for_user {
+            hdr.push("Source");
+            properties.push("source");
+        }
+        if show_times {
+            hdr.push("Created At");
+            hdr.push("Modified At");
+            properties.push("created-at");
+            properties.push("modified-at");
+        }
+        let mut table = Table::new("grant");
+        table.set_header(&hdr);
+        for entry in details {
+            table.add_row(entry.get_properties(&properties));
+        }
+        table.render(fmt)?;
+    }
+    Ok(())
+}
+
+fn proc_grants_set(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    grants: &Grants,
+) -> Result<()> {
+    let role = subcmd_args.value_of(ROLE_ARG).unwrap();
+    let principal = resolve_principal(subcmd_args, rest_cfg)?.unwrap();
+    let scope = resolve_scope(subcmd_args, rest_cfg)?.unwrap();
+
+    if let Some(grant_id) = grants.get_id(rest_cfg, &principal.url, &scope.url)? {
+        grants.update_grant(rest_cfg, &grant_id, role)?;
+        println!(
+            "Updated grant for {} on {} to {role}",
+            principal.display(),
+            scope.display()
+        );
+    } else {
+        grants.create_grant(rest_cfg, &principal.url, &scope.url, role)?;
+        println!(
+            "Created grant for {} on {} as {role}",
+            principal.display(),
+            scope.display()
+        );
+    }
+    Ok(())
+}
+
+/// Process the 'grants' sub-command
+pub fn process_grants_command(subcmd_args: &ArgMatches, rest_cfg: &OpenApiConfig) -> Result<()> {
+    let grants = Grants::new();
+    if let Some(subcmd_args) = subcmd_args.subcommand_matches(LIST_SUBCMD) {
+        proc_grants_list(subcmd_args, rest_cfg, &grants)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(DELETE_SUBCMD) {
+        proc_grants_delete(subcmd_args, rest_cfg, &grants)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(SET_SUBCMD) {
+        proc_grants_set(subcmd_args, rest_cfg, &grants)?;
+    } else {
+        warn_missing_subcommand("grants");
+    }
+    Ok(())
+}
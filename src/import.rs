@@ -1,10 +1,13 @@
 use crate::cli::{FORMAT_OPT, SECRETS_FLAG, SHOW_TIMES_FLAG};
 use crate::database::{Imports, OpenApiConfig};
+use crate::import_parsers::{parse_import, ImportFormat};
 use crate::table::Table;
-use crate::utils::{warn_missing_subcommand, FILE_READ_ERR};
+use crate::utils::{error_message, warn_missing_subcommand, FILE_READ_ERR};
 use clap::ArgMatches;
 use color_eyre::eyre::Result;
 use std::fs;
+use std::process;
+use std::str::FromStr;
 
 fn proc_import_parameters(
     subcmd_args: &ArgMatches,
@@ -19,15 +22,34 @@ fn proc_import_parameters(
         .values_of("ignore-param")
         .unwrap_or_default()
         .collect();
-    let secret_params: Vec<&str> = subcmd_args
-        .values_of("secret-param")
-        .unwrap_or_default()
-        .collect();
+    let mut secret_params: Vec<String> = subcmd_args
+        .values_of_lossy("secret-param")
+        .unwrap_or_default();
     let inherit = !subcmd_args.is_present("no-inherit");
     let show_times = subcmd_args.is_present(SHOW_TIMES_FLAG);
     let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
     let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
-    let text = fs::read_to_string(filename).expect(FILE_READ_ERR);
+    let mut text = fs::read_to_string(filename).expect(FILE_READ_ERR);
+
+    // parse the file here for the formats the server does not understand, and send the values as
+    // a flat JSON object
+    if let Some(input_format) = subcmd_args.value_of("input-format") {
+        let format = ImportFormat::from_str(input_format).unwrap();
+        let parsed = match parse_import(&text, format, subcmd_args.value_of("delimiter")) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                error_message(e.to_string());
+                process::exit(79);
+            }
+        };
+        for name in &parsed.secrets {
+            if !secret_params.contains(name) {
+                secret_params.push(name.clone());
+            }
+        }
+        text = parsed.to_import_body();
+    }
+    let secret_params: Vec<&str> = secret_params.iter().map(String::as_str).collect();
 
     let details = imports.import_parameters(
         rest_cfg,
@@ -0,0 +1,732 @@
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+/// Delimiter between the levels of flattened keys, when none is given.
+pub const DEFAULT_FLATTEN_DELIMITER: &str = ".";
+
+#[derive(Debug)]
+pub struct ImportParseError(pub String);
+
+impl error::Error for ImportParseError {}
+
+impl fmt::Display for ImportParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "Failed to parse the import file: {}", self.0)
+    }
+}
+
+type ParseResult<T> = Result<T, ImportParseError>;
+
+fn parse_error<T, S: Into<String>>(message: S) -> ParseResult<T> {
+    Err(ImportParseError(message.into()))
+}
+
+/// Formats parsed by the CLI, instead of sending the file to the server as is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    Json,
+    Yaml,
+    Properties,
+    Tfvars,
+    Kubernetes,
+}
+
+impl FromStr for ImportFormat {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "properties" => Ok(Self::Properties),
+            "tfvars" => Ok(Self::Tfvars),
+            "kubernetes" => Ok(Self::Kubernetes),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The parameter values from the file, along with the names of the values known to be secrets.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedImport {
+    pub values: BTreeMap<String, String>,
+    pub secrets: BTreeSet<String>,
+}
+
+impl ParsedImport {
+    fn insert(&mut self, name: String, value: String) -> ParseResult<()> {
+        if self.values.contains_key(&name) {
+            return parse_error(format!("'{name}' is defined more than once"));
+        }
+        self.values.insert(name, value);
+        Ok(())
+    }
+
+    /// Renders the values as a flat JSON object for the server import, which keeps any name and
+    /// value as is.
+    pub fn to_import_body(&self) -> String {
+        serde_json::to_string(&self.values).unwrap()
+    }
+}
+
+pub fn parse_import(
+    text: &str,
+    format: ImportFormat,
+    delimiter: Option<&str>,
+) -> ParseResult<ParsedImport> {
+    let delimiter = delimiter
+        .filter(|d| !d.is_empty())
+        .unwrap_or(DEFAULT_FLATTEN_DELIMITER);
+    let mut result = ParsedImport::default();
+    match format {
+        // JSON is a subset of YAML, and both are flattened the same way
+        ImportFormat::Json | ImportFormat::Yaml => {
+            let value: Value =
+                serde_yaml::from_str(text).map_err(|e| ImportParseError(e.to_string()))?;
+            match value {
+                Value::Mapping(_) => flatten(&mut result, "", &value, delimiter)?,
+                Value::Null => {}
+                _ => return parse_error("expected an object of parameter names and values"),
+            }
+        }
+        ImportFormat::Properties => parse_properties(&mut result, text)?,
+        ImportFormat::Tfvars => {
+            let mut parser = HclParser::new(text);
+            parser.parse_body(&mut result, "", delimiter, None)?;
+        }
+        ImportFormat::Kubernetes => {
+            for document in serde_yaml::Deserializer::from_str(text) {
+                let value =
+                    Value::deserialize(document).map_err(|e| ImportParseError(e.to_string()))?;
+                parse_kubernetes_object(&mut result, &value)?;
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn scalar_string(value: &Value) -> ParseResult<String> {
+    match value {
+        Value::Null => Ok(String::new()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        // lists are kept as JSON text
+        Value::Sequence(_) => {
+            serde_json::to_string(value).map_err(|e| ImportParseError(e.to_string()))
+        }
+        Value::Mapping(_) => parse_error("unexpected nested object"),
+    }
+}
+
+/// Adds the values of the nested maps, with the keys joined by the `delimiter`.
+fn flatten(
+    result: &mut ParsedImport,
+    prefix: &str,
+    value: &Value,
+    delimiter: &str,
+) -> ParseResult<()> {
+    match value {
+        Value::Mapping(map) => {
+            for (key, value) in map {
+                let key = scalar_string(key)?;
+                let name = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}{delimiter}{key}")
+                };
+                flatten(result, &name, value, delimiter)?;
+            }
+            Ok(())
+        }
+        _ => result.insert(prefix.to_string(), scalar_string(value)?),
+    }
+}
+
+/// Adds the `data` of ConfigMap and Secret objects (including those in a List). Secret `data`
+/// and `stringData` values are secrets.
+fn parse_kubernetes_object(result: &mut ParsedImport, object: &Value) -> ParseResult<()> {
+    let kind = object
+        .get("kind")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let entries = |field: &str| -> ParseResult<Vec<(String, String)>> {
+        match object.get(field) {
+            None | Some(Value::Null) => Ok(vec![]),
+            Some(Value::Mapping(map)) => map
+                .iter()
+                .map(|(k, v)| Ok((scalar_string(k)?, scalar_string(v)?)))
+                .collect(),
+            Some(_) => parse_error(format!("the {kind} '{field}' is not a map")),
+        }
+    };
+    let decode = |name: &str, value: &str| -> ParseResult<String> {
+        let bytes = base64::decode(value.trim())
+            .map_err(|e| ImportParseError(format!("'{name}' is not valid base64: {e}")))?;
+        String::from_utf8(bytes)
+            .map_err(|_| ImportParseError(format!("'{name}' is not UTF-8 text")))
+    };
+    match kind {
+        "List" => {
+            if let Some(Value::Sequence(items)) = object.get("items") {
+                for item in items {
+                    parse_kubernetes_object(result, item)?;
+                }
+            }
+        }
+        "ConfigMap" => {
+            for (name, value) in entries("data")? {
+                result.insert(name, value)?;
+            }
+            for (name, value) in entries("binaryData")? {
+                let value = decode(&name, &value)?;
+                result.insert(name, value)?;
+            }
+        }
+        "Secret" => {
+            let mut data_names = BTreeSet::new();
+            for (name, value) in entries("data")? {
+                let value = decode(&name, &value)?;
+                result.secrets.insert(name.clone());
+                data_names.insert(name.clone());
+                result.insert(name, value)?;
+            }
+            // like kubernetes, the stringData takes precedence over the data of the same secret
+            for (name, value) in entries("stringData")? {
+                result.secrets.insert(name.clone());
+                if data_names.contains(&name) {
+                    result.values.insert(name, value);
+                } else {
+                    result.insert(name, value)?;
+                }
+            }
+        }
+        "" if object.is_null() => {}
+        _ => {
+            return parse_error(format!(
+                "unsupported kubernetes kind '{kind}', expected ConfigMap, Secret or List"
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Splits the text into logical lines, joining the lines continued with a trailing backslash.
+fn properties_lines(text: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let line = match &current {
+            // leading whitespace of continuation lines is ignored
+            Some(_) => line.trim_start(),
+            None => {
+                let trimmed = line.trim_start();
+                if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+                    continue;
+                }
+                trimmed
+            }
+        };
+        let trailing = line.len() - line.trim_end_matches('\\').len();
+        let mut joined = current.take().unwrap_or_default();
+        if trailing % 2 == 1 {
+            joined.push_str(&line[..line.len() - 1]);
+            current = Some(joined);
+        } else {
+            joined.push_str(line);
+            lines.push(joined);
+        }
+    }
+    if let Some(last) = current {
+        lines.push(last);
+    }
+    lines
+}
+
+/// Reads an escaped character, after the backslash.
+fn properties_escape(chars: &mut Peekable<Chars>) -> ParseResult<Option<char>> {
+    let c = match chars.next() {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    Ok(Some(match c {
+        't' => '\t',
+        'n' => '\n',
+        'r' => '\r',
+        'f' => '\x0c',
+        'u' => {
+            let hex: String = chars.by_ref().take(4).collect();
+            let unit = u16::from_str_radix(&hex, 16)
+                .map_err(|_| ImportParseError(format!("invalid unicode escape '\\u{hex}'")))?;
+            // surrogate pairs are two escapes
+            if (0xD800..0xDC00).contains(&unit) {
+                let mut rest = chars.clone();
+                if rest.next() == Some('\\') && rest.next() == Some('u') {
+                    let low: String = rest.by_ref().take(4).collect();
+                    if let Ok(low) = u16::from_str_radix(&low, 16) {
+                        if let Some(Ok(c)) = char::decode_utf16([unit, low]).next() {
+                            *chars = rest;
+                            return Ok(Some(c));
+                        }
+                    }
+                }
+            }
+            char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+        }
+        c => c,
+    }))
+}
+
+/// Parses Java `.properties` text, with the same separators and escapes as `Properties.load()`.
+fn parse_properties(result: &mut ParsedImport, text: &str) -> ParseResult<()> {
+    for line in properties_lines(text) {
+        let mut chars = line.chars().peekable();
+        let mut key = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => key.extend(properties_escape(&mut chars)?),
+                '=' | ':' => break,
+                c if c.is_whitespace() => {
+                    // whitespace may surround the separator, or be the separator
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    if let Some('=') | Some(':') = chars.peek() {
+                        chars.next();
+                    }
+                    break;
+                }
+                c => key.push(c),
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => value.extend(properties_escape(&mut chars)?),
+                c => value.push(c),
+            }
+        }
+        // later definitions replace earlier ones, like Properties.load()
+        result.values.insert(key, value);
+    }
+    Ok(())
+}
+
+/// A parser for the subset of HCL used in `.tfvars` files: assignments of strings, numbers,
+/// booleans, heredocs, lists and (flattened) objects.
+struct HclParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> HclParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> ParseResult<T> {
+        parse_error(format!("line {}: {}", self.line, message))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips whitespace and comments, and newlines (and commas) when `separators` is set.
+    fn skip(&mut self, separators: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\n' | ',' if !separators => return,
+                c if c.is_whitespace() || c == ',' => {
+                    self.next();
+                }
+                '#' => self.skip_line(),
+                '/' => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    match ahead.next() {
+                        Some('/') => self.skip_line(),
+                        Some('*') => {
+                            self.next();
+                            self.next();
+                            let mut previous = ' ';
+                            while let Some(c) = self.next() {
+                                if previous == '*' && c == '/' {
+                                    break;
+                                }
+                                previous = c;
+                            }
+                        }
+                        _ => return,
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '\n' {
+                return;
+            }
+            self.next();
+        }
+    }
+
+    fn parse_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || "_-.+".contains(c) {
+                word.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn parse_string(&mut self) -> ParseResult<String> {
+        self.next(); // opening quote
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('"') => return Ok(result),
+                Some('\\') => match self.next() {
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => result.push(c),
+                            None => return self.error(&format!("invalid escape '\\u{hex}'")),
+                        }
+                    }
+                    Some(c) => result.push(c),
+                    None => return self.error("unterminated string"),
+                },
+                // '$${' and '%%{' are the escaped template sequences
+                Some(c) if (c == '$' || c == '%') && self.chars.peek() == Some(&c) => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    if ahead.next() == Some('{') {
+                        self.next();
+                    }
+                    result.push(c);
+                }
+                Some(c) => result.push(c),
+            }
+        }
+    }
+
+    fn parse_heredoc(&mut self) -> ParseResult<String> {
+        self.next();
+        self.next(); // '<<'
+        let indented = self.chars.next_if_eq(&'-').is_some();
+        let marker = self.parse_word();
+        if marker.is_empty() {
+            return self.error("missing heredoc marker");
+        }
+        self.skip_line();
+        self.next();
+        let mut lines: Vec<String> = vec![];
+        loop {
+            let mut line = String::new();
+            while let Some(&c) = self.chars.peek() {
+                if c == '\n' {
+                    break;
+                }
+                line.push(c);
+                self.next();
+            }
+            let at_end = self.next().is_none();
+            if line.trim() == marker {
+                break;
+            }
+            if at_end {
+                return self.error(&format!("missing heredoc end marker '{marker}'"));
+            }
+            lines.push(line);
+        }
+        if indented {
+            let indent = lines
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.len() - l.trim_start().len())
+                .min()
+                .unwrap_or_default();
+            for line in lines.iter_mut() {
+                *line = line.chars().skip(indent).collect();
+            }
+        }
+        Ok(lines.iter().map(|l| l.to_string() + "\n").collect())
+    }
+
+    fn parse_key(&mut self) -> ParseResult<String> {
+        match self.chars.peek() {
+            Some('"') => self.parse_string(),
+            Some(_) => {
+                let word = self.parse_word();
+                if word.is_empty() {
+                    self.error("expected a variable name")
+                } else {
+                    Ok(word)
+                }
+            }
+            None => self.error("expected a variable name"),
+        }
+    }
+
+    /// Parses `name = value` assignments until the end of the text, or the `end` character.
+    fn parse_body(
+        &mut self,
+        result: &mut ParsedImport,
+        prefix: &str,
+        delimiter: &str,
+        end: Option<char>,
+    ) -> ParseResult<()> {
+        loop {
+            self.skip(true);
+            match (self.chars.peek().copied(), end) {
+                (None, None) => return Ok(()),
+                (None, Some(_)) => return self.error("unterminated object"),
+                (Some(c), Some(end)) if c == end => {
+                    self.next();
+                    return Ok(());
+                }
+                _ => {}
+            }
+            let key = self.parse_key()?;
+            let name = if prefix.is_empty() {
+                key
+            } else {
+                format!("{prefix}{delimiter}{key}")
+            };
+            self.skip(false);
+            match self.next() {
+                Some('=') | Some(':') => {}
+                _ => return self.error(&format!("expected '=' after '{name}'")),
+            }
+            self.skip(false);
+            match self.chars.peek() {
+                Some('{') => {
+                    self.next();
+                    self.parse_body(result, &name, delimiter, Some('}'))?;
+                }
+                _ => {
+                    let value = self.parse_value()?;
+                    result.insert(name, value)?;
+                }
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<String> {
+        match self.chars.peek() {
+            Some('"') => self.parse_string(),
+            Some('<') => self.parse_heredoc(),
+            Some('[') => {
+                // lists are kept as JSON text
+                self.next();
+                let mut items: Vec<String> = vec![];
+                loop {
+                    self.skip(true);
+                    if self.chars.next_if_eq(&']').is_some() {
+                        break;
+                    }
+                    if self.chars.peek().is_none() {
+                        return self.error("unterminated list");
+                    }
+                    items.push(self.parse_value()?);
+                }
+                Ok(serde_json::to_string(&items).unwrap())
+            }
+            Some(_) => {
+                let word = self.parse_word();
+                match word.as_str() {
+                    "true" | "false" | "null" => Ok(word),
+                    _ if word.parse::<f64>().is_ok() => Ok(word),
+                    _ => self.error(&format!("unsupported value '{word}'")),
+                }
+            }
+            None => self.error("expected a value"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn import_json_and_yaml() {
+        let text =
+            r#"{"db": {"host": "localhost", "port": 5432}, "debug": true, "tags": ["a", "b"]}"#;
+        let parsed = parse_import(text, ImportFormat::Json, None).unwrap();
+        assert_eq!(
+            parsed.values,
+            values(&[
+                ("db.host", "localhost"),
+                ("db.port", "5432"),
+                ("debug", "true"),
+                ("tags", r#"["a","b"]"#),
+            ])
+        );
+
+        let text = "APP:\n  DB:\n    HOST: db\n  NAME: |\n    multi\n    line\n";
+        let parsed = parse_import(text, ImportFormat::Yaml, Some("__")).unwrap();
+        assert_eq!(
+            parsed.values,
+            values(&[("APP__DB__HOST", "db"), ("APP__NAME", "multi\nline\n")])
+        );
+        assert!(parse_import("- a\n- b\n", ImportFormat::Yaml, None).is_err());
+        assert!(parse_import("a.b: 1\na:\n  b: 2\n", ImportFormat::Yaml, None).is_err());
+    }
+
+    #[test]
+    fn import_properties() {
+        let text = concat!(
+            "# comment\n",
+            "! another\n",
+            "url = http\\://host\\:80/a\\=b\n",
+            "key\\ with\\ spaces:value\n",
+            "multi = first \\\n",
+            "        second\n",
+            "unicode caf\\u00e9 \\uD83D\\uDE00\n",
+            "empty\n",
+        );
+        let parsed = parse_import(text, ImportFormat::Properties, None).unwrap();
+        assert_eq!(
+            parsed.values,
+            values(&[
+                ("url", "http://host:80/a=b"),
+                ("key with spaces", "value"),
+                ("multi", "first second"),
+                ("unicode", "café 😀"),
+                ("empty", ""),
+            ])
+        );
+    }
+
+    #[test]
+    fn import_tfvars() {
+        let text = r#"
+# comment
+region = "us-east-1" // trailing comment
+count  = 3
+enabled = true
+message = "say \"hi\"\n$${literal}"
+/* block
+   comment */
+zones = ["a", "b"]
+tags = {
+  owner = "infra"
+  "cost-center" = 42,
+}
+script = <<-EOT
+    echo one
+      echo two
+    EOT
+"#;
+        let parsed = parse_import(text, ImportFormat::Tfvars, None).unwrap();
+        assert_eq!(
+            parsed.values,
+            values(&[
+                ("region", "us-east-1"),
+                ("count", "3"),
+                ("enabled", "true"),
+                ("message", "say \"hi\"\n${literal}"),
+                ("zones", r#"["a","b"]"#),
+                ("tags.owner", "infra"),
+                ("tags.cost-center", "42"),
+                ("script", "echo one\n  echo two\n"),
+            ])
+        );
+        assert!(parse_import("name = var.other\n", ImportFormat::Tfvars, None).is_err());
+        assert!(parse_import("name = \"open\n", ImportFormat::Tfvars, None).is_err());
+    }
+
+    #[test]
+    fn import_kubernetes() {
+        let text = format!(
+            concat!(
+                "apiVersion: v1\n",
+                "kind: ConfigMap\n",
+                "metadata:\n  name: app\n",
+                "data:\n  HOST: example.com\n",
+                "---\n",
+                "apiVersion: v1\n",
+                "kind: Secret\n",
+                "metadata:\n  name: app\n",
+                "data:\n  PASSWORD: {}\n  USER: {}\n",
+                "stringData:\n  TOKEN: plain\n  USER: admin\n",
+            ),
+            base64::encode("hunter22"),
+            base64::encode("nobody")
+        );
+        let parsed = parse_import(&text, ImportFormat::Kubernetes, None).unwrap();
+        assert_eq!(
+            parsed.values,
+            values(&[
+                ("HOST", "example.com"),
+                ("PASSWORD", "hunter22"),
+                ("TOKEN", "plain"),
+                ("USER", "admin"),
+            ])
+        );
+        assert_eq!(
+            parsed.secrets,
+            BTreeSet::from([
+                "PASSWORD".to_string(),
+                "TOKEN".to_string(),
+                "USER".to_string()
+            ])
+        );
+        // the same name in different objects is still a conflict
+        let twice = "kind: ConfigMap\ndata:\n  A: one\n---\nkind: Secret\nstringData:\n  A: two\n";
+        assert!(parse_import(twice, ImportFormat::Kubernetes, None).is_err());
+
+        let list = "kind: List\nitems:\n  - kind: Secret\n    data:\n      BAD: '***'\n";
+        assert!(parse_import(list, ImportFormat::Kubernetes, None).is_err());
+        assert!(parse_import("kind: Deployment\n", ImportFormat::Kubernetes, None).is_err());
+    }
+
+    #[test]
+    fn import_body_round_trip() {
+        let parsed = ParsedImport {
+            values: values(&[
+                ("A", "it's \"quoted\"\nnext"),
+                ("B", "back\\slash"),
+                ("with space", "tab\there\r\n"),
+                ("key=value", "${HOME} and $PATH"),
+                ("# comment", "value # not a comment"),
+            ]),
+            ..Default::default()
+        };
+        let body: BTreeMap<String, String> =
+            serde_json::from_str(&parsed.to_import_body()).unwrap();
+        assert_eq!(body, parsed.values);
+    }
+}
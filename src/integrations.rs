@@ -1,10 +1,13 @@
 use crate::cli::{
-    show_values, FORMAT_OPT, GET_SUBCMD, INTEGRATION_NAME_ARG, JMES_PATH_ARG, LIST_SUBCMD,
-    RAW_FLAG, SECRETS_FLAG, SHOW_TIMES_FLAG,
+    show_values, CONFIRM_FLAG, DELETE_SUBCMD, DESCRIPTION_OPT, FORMAT_OPT, GET_SUBCMD,
+    INTEGRATION_NAME_ARG, JMES_PATH_ARG, LIST_SUBCMD, RAW_FLAG, SECRETS_FLAG, SET_SUBCMD,
+    SHOW_TIMES_FLAG,
 };
-use crate::database::{Integrations, OpenApiConfig};
+use crate::database::{IntegrationDetails, Integrations, OpenApiConfig};
 use crate::table::Table;
-use crate::utils::{error_message, warn_missing_subcommand, warning_message};
+use crate::utils::{
+    error_message, user_confirm, warn_missing_subcommand, warning_message, DEL_CONFIRM,
+};
 use clap::ArgMatches;
 use color_eyre::eyre::Result;
 use indoc::printdoc;
@@ -14,6 +17,33 @@ pub fn integration_not_found_message(integ_name: &str) -> String {
     format!("Integration '{integ_name}' not found")
 }
 
+fn proc_integ_delete(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    integrations: &Integrations,
+) -> Result<()> {
+    let integ_name = subcmd_args.value_of(INTEGRATION_NAME_ARG).unwrap();
+    let in_use = subcmd_args.value_of("in-use");
+    let details = integrations.get_details_by_name(rest_cfg, integ_name)?;
+
+    if let Some(details) = details {
+        let mut confirmed = subcmd_args.is_present(CONFIRM_FLAG);
+        if !confirmed {
+            confirmed = user_confirm(format!("Delete integration '{integ_name}'"), DEL_CONFIRM);
+        }
+
+        if !confirmed {
+            warning_message(format!("Integration '{integ_name}' not deleted!"));
+        } else {
+            integrations.delete_integration(rest_cfg, &details, in_use)?;
+            println!("Deleted integration '{integ_name}'");
+        }
+    } else {
+        warning_message(format!("Integration '{integ_name}' does not exist!"));
+    }
+    Ok(())
+}
+
 fn proc_integ_explore(
     subcmd_args: &ArgMatches,
     rest_cfg: &OpenApiConfig,
@@ -180,13 +210,134 @@ fn proc_integ_refresh(
     Ok(())
 }
 
+fn proc_integ_scan(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    integrations: &Integrations,
+) -> Result<()> {
+    let integ_name = subcmd_args.value_of(INTEGRATION_NAME_ARG).unwrap();
+    let resource = subcmd_args.value_of("resource").unwrap();
+    let region = subcmd_args.value_of("region").unwrap();
+    let service = subcmd_args.value_of("service").unwrap();
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+
+    let details = match integrations.get_details_by_name(rest_cfg, integ_name)? {
+        Some(details) => details,
+        None => {
+            error_message(integration_not_found_message(integ_name));
+            process::exit(32);
+        }
+    };
+    if details.provider == "github" {
+        error_message(format!(
+            "Integration '{integ_name}' is a GitHub integration, which cannot be scanned"
+        ));
+        process::exit(69);
+    }
+    let entries = integrations.scan(rest_cfg, &details, resource, region, service)?;
+    if entries.is_empty() {
+        println!("No external values found for '{resource}' in integration '{integ_name}'");
+    } else {
+        let hdr = &[
+            "External Value",
+            "Project",
+            "Environment",
+            "Parameter",
+            "Status",
+            "Details",
+        ];
+        let properties = &[
+            "venue-name",
+            "project",
+            "environment",
+            "parameter",
+            "status",
+            "details",
+        ];
+        let mut table = Table::new("scan");
+        table.set_header(hdr);
+        for entry in entries {
+            let mut row = entry.get_properties(properties);
+            if entry.is_skipped() {
+                row[0] = entry.fqn.clone();
+            }
+            table.add_row(row);
+        }
+        table.render(fmt)?;
+    }
+    Ok(())
+}
+
+fn proc_integ_set(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    integrations: &Integrations,
+) -> Result<()> {
+    let created: IntegrationDetails;
+    if let Some(provider_args) = subcmd_args.subcommand_matches("aws") {
+        let account_id = provider_args.value_of("account-id").unwrap();
+        let role_name = provider_args.value_of("role-name").unwrap();
+        let regions = provider_args.values_of_lossy("region").unwrap();
+        let services = provider_args.values_of_lossy("service").unwrap();
+        let description = provider_args.value_of(DESCRIPTION_OPT);
+        let writable = provider_args.value_of("writable").map(|v| v == "true");
+        created = integrations.create_aws_integration(
+            rest_cfg,
+            account_id,
+            role_name,
+            &regions,
+            &services,
+            description,
+            writable,
+        )?;
+    } else if let Some(provider_args) = subcmd_args.subcommand_matches("azure") {
+        let tenant = provider_args.value_of("tenant").unwrap();
+        let vault = provider_args.value_of("vault").unwrap();
+        let description = provider_args.value_of(DESCRIPTION_OPT);
+        let writable = provider_args.value_of("writable").map(|v| v == "true");
+        created = integrations.create_azure_integration(
+            rest_cfg,
+            tenant,
+            vault,
+            description,
+            writable,
+        )?;
+    } else if let Some(provider_args) = subcmd_args.subcommand_matches("github") {
+        let installation = provider_args.value_of("installation").unwrap();
+        let description = provider_args.value_of(DESCRIPTION_OPT);
+        let writable = provider_args.value_of("writable").map(|v| v == "true");
+        let installation_id = match installation.parse::<i32>() {
+            Ok(id) => id,
+            Err(_) => {
+                error_message(format!(
+                    "GitHub installation '{installation}' is not a valid identifier"
+                ));
+                process::exit(68);
+            }
+        };
+        created = integrations.create_github_integration(
+            rest_cfg,
+            installation_id,
+            description,
+            writable,
+        )?;
+    } else {
+        warn_missing_subcommand("integrations set");
+        return Ok(());
+    }
+    println!("Created integration '{}'", created.name);
+    Ok(())
+}
+
 /// Process the 'integrations' sub-command
 pub fn process_integrations_command(
     subcmd_args: &ArgMatches,
     rest_cfg: &OpenApiConfig,
 ) -> Result<()> {
     let integrations = Integrations::new();
-    if let Some(subcmd_args) = subcmd_args.subcommand_matches("explore") {
+    if let Some(subcmd_args) = subcmd_args.subcommand_matches(DELETE_SUBCMD) {
+        proc_integ_delete(subcmd_args, rest_cfg, &integrations)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("explore") {
         proc_integ_explore(subcmd_args, rest_cfg, &integrations)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(GET_SUBCMD) {
         proc_integ_get(subcmd_args, rest_cfg, &integrations)?;
@@ -194,6 +345,10 @@ pub fn process_integrations_command(
         proc_integ_list(subcmd_args, rest_cfg, &integrations)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("refresh") {
         proc_integ_refresh(subcmd_args, rest_cfg, &integrations)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("scan") {
+        proc_integ_scan(subcmd_args, rest_cfg, &integrations)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(SET_SUBCMD) {
+        proc_integ_set(subcmd_args, rest_cfg, &integrations)?;
     } else {
         warn_missing_subcommand("integrations");
     }
@@ -0,0 +1,239 @@
+use crate::cli::{KEY_PREFIX_OPT, KEY_SANITIZE_FLAG, KEY_STRIP_PREFIX_OPT, KEY_UPPERCASE_FLAG};
+use crate::subprocess::EnvSettings;
+use clap::ArgMatches;
+use cloudtruth_config::Config;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Two or more parameter names that end up with the same name after the transforms.
+#[derive(Debug)]
+pub struct KeyCollisions(pub BTreeMap<String, Vec<String>>);
+
+impl error::Error for KeyCollisions {}
+
+impl fmt::Display for KeyCollisions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let details: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, originals)| format!("{} ({})", name, originals.join(", ")))
+            .collect();
+        write!(
+            f,
+            "Key name transforms produce duplicate names: {}",
+            details.join(", ")
+        )
+    }
+}
+
+/// Rules for renaming parameters when they are used as variable names.
+///
+/// The prefix is stripped first, then the new prefix is added, and finally the name is
+/// uppercased and/or has invalid characters replaced.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyTransform {
+    pub prefix: Option<String>,
+    pub strip_prefix: Option<String>,
+    pub uppercase: bool,
+    pub sanitize: bool,
+}
+
+impl KeyTransform {
+    /// Gets the transforms from the command line, falling back to the profile settings.
+    pub fn new(args: &ArgMatches, config: &Config) -> Self {
+        Self {
+            prefix: args
+                .value_of(KEY_PREFIX_OPT)
+                .map(String::from)
+                .or_else(|| config.key_prefix.clone()),
+            strip_prefix: args
+                .value_of(KEY_STRIP_PREFIX_OPT)
+                .map(String::from)
+                .or_else(|| config.key_strip_prefix.clone()),
+            uppercase: args.is_present(KEY_UPPERCASE_FLAG) || config.key_uppercase,
+            sanitize: args.is_present(KEY_SANITIZE_FLAG) || config.key_sanitize,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn transform_key(&self, key: &str) -> String {
+        let mut result = match &self.strip_prefix {
+            Some(strip) => key.strip_prefix(strip.as_str()).unwrap_or(key).to_string(),
+            None => key.to_string(),
+        };
+        if let Some(prefix) = &self.prefix {
+            result = format!("{prefix}{result}");
+        }
+        if self.uppercase {
+            result = result.to_uppercase();
+        }
+        if self.sanitize {
+            result = result
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+                result.insert(0, '_');
+            }
+        }
+        result
+    }
+
+    /// Renames all the keys, and fails when different keys end up with the same name.
+    pub fn apply(&self, values: EnvSettings) -> Result<EnvSettings, KeyCollisions> {
+        if self.is_empty() {
+            return Ok(values);
+        }
+        check_collisions(
+            values
+                .keys()
+                .map(|k| (k.clone(), self.transform_key(k)))
+                .collect(),
+        )?;
+        Ok(values
+            .into_iter()
+            .map(|(k, v)| (self.transform_key(&k), v))
+            .collect())
+    }
+
+    /// Renames the variables in the server export output (e.g. `export NAME=value`). When the
+    /// values are `quoted` (dotenv and shell), quoted values may span several lines.
+    pub fn apply_to_export(&self, body: &str, quoted: bool) -> Result<String, KeyCollisions> {
+        if self.is_empty() {
+            return Ok(body.to_string());
+        }
+        let mut result = String::new();
+        let mut names: Vec<(String, String)> = vec![];
+        let mut quote: Option<char> = None;
+        for line in body.split_inclusive('\n') {
+            let mut rest = line;
+            if quote.is_none() {
+                let (export, assignment) = match line.strip_prefix("export ") {
+                    Some(assignment) => ("export ", assignment),
+                    None => ("", line),
+                };
+                if let Some((name, value)) = assignment.split_once('=') {
+                    if !name.is_empty() && !name.contains(char::is_whitespace) {
+                        let new_name = self.transform_key(name);
+                        result.push_str(export);
+                        result.push_str(&new_name);
+                        result.push('=');
+                        names.push((name.to_string(), new_name));
+                        rest = value;
+                    }
+                }
+            }
+            if quoted {
+                quote = scan_quotes(rest, quote);
+            }
+            result.push_str(rest);
+        }
+        check_collisions(names)?;
+        Ok(result)
+    }
+}
+
+/// Tracks whether the text leaves us inside a single- or double-quoted string.
+fn scan_quotes(text: &str, mut quote: Option<char>) -> Option<char> {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some('"'), '"') => quote = None,
+            (None, '\'') | (None, '"') => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote
+}
+
+fn check_collisions(names: Vec<(String, String)>) -> Result<(), KeyCollisions> {
+    let mut renamed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (orig, name) in names {
+        renamed.entry(name).or_default().push(orig);
+    }
+    renamed.retain(|_, originals| originals.len() > 1);
+    if renamed.is_empty() {
+        Ok(())
+    } else {
+        renamed.values_mut().for_each(|originals| originals.sort());
+        Err(KeyCollisions(renamed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_keys() {
+        let transform = KeyTransform {
+            prefix: Some("app_".to_string()),
+            strip_prefix: Some("legacy.".to_string()),
+            uppercase: true,
+            sanitize: true,
+        };
+        assert_eq!(transform.transform_key("legacy.db.host"), "APP_DB_HOST");
+        assert_eq!(transform.transform_key("api-key"), "APP_API_KEY");
+
+        let transform = KeyTransform {
+            sanitize: true,
+            ..Default::default()
+        };
+        assert_eq!(transform.transform_key("db.host"), "db_host");
+        assert_eq!(transform.transform_key("9lives"), "_9lives");
+        assert_eq!(KeyTransform::default().transform_key("a.b"), "a.b");
+    }
+
+    #[test]
+    fn transform_collisions() {
+        let transform = KeyTransform {
+            uppercase: true,
+            sanitize: true,
+            ..Default::default()
+        };
+        let mut values = EnvSettings::new();
+        values.insert("db.host".to_string(), "a".to_string());
+        values.insert("other".to_string(), "b".to_string());
+        let result = transform.apply(values.clone()).unwrap();
+        assert_eq!(result.get("DB_HOST").unwrap(), "a");
+
+        values.insert("DB-HOST".to_string(), "c".to_string());
+        let err = transform.apply(values).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Key name transforms produce duplicate names: DB_HOST (DB-HOST, db.host)"
+        );
+    }
+
+    #[test]
+    fn transform_export() {
+        let transform = KeyTransform {
+            prefix: Some("MY_".to_string()),
+            strip_prefix: Some("APP_".to_string()),
+            ..Default::default()
+        };
+        let body = "export APP_ONE='first\nNOT_A_KEY=line'\nexport TWO=\"it's\"\n";
+        let result = transform.apply_to_export(body, true).unwrap();
+        assert_eq!(
+            result,
+            "export MY_ONE='first\nNOT_A_KEY=line'\nexport MY_TWO=\"it's\"\n"
+        );
+
+        let body = "APP_A=it's\nB=b\n";
+        let result = transform.apply_to_export(body, false).unwrap();
+        assert_eq!(result, "MY_A=it's\nMY_B=b\n");
+
+        let body = "APP_A=1\nA=2\n";
+        assert!(transform.apply_to_export(body, false).is_err());
+    }
+}
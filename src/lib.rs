@@ -8,15 +8,23 @@ mod configuration;
 mod database;
 mod environments;
 mod generate;
+mod grants;
 mod groups;
 mod import;
+mod import_parsers;
 mod integrations;
+mod key_transform;
+mod lock;
 mod login;
 mod logout;
+mod offline_cache;
 mod parameters;
 mod projects;
+mod redactor;
 mod run;
+mod run_templates;
 mod schema;
+mod shell_hook;
 mod subprocess;
 mod table;
 mod templates;
@@ -29,18 +37,22 @@ use crate::actions::process_actions_command;
 use crate::audit_logs::process_audit_log_command;
 use crate::backup::process_backup_command;
 use crate::configuration::process_config_command;
-use crate::database::{OpenApiConfig, Resolver};
+use crate::database::OpenApiConfig;
 use crate::environments::process_environment_command;
 use crate::generate::process_generate_command;
+use crate::grants::process_grants_command;
 use crate::groups::process_groups_command;
 use crate::import::process_import_command;
 use crate::integrations::process_integrations_command;
+use crate::lock::process_lock_command;
 use crate::login::process_login_command;
 use crate::logout::process_logout_command;
+use crate::offline_cache::{cached_resolve_ids, OfflineCache};
 use crate::parameters::process_parameters_command;
 use crate::projects::process_project_command;
 use crate::run::process_run_command;
 use crate::schema::process_schema_command;
+use crate::shell_hook::process_shell_hook_command;
 use crate::templates::process_templates_command;
 use crate::types::process_parameter_type_command;
 use crate::users::process_users_command;
@@ -176,6 +188,12 @@ pub fn main() -> Result<()> {
         process::exit(0)
     }
 
+    // the hook output is evaluated by the shell, so this is handled before the update checks
+    if let Some(matches) = matches.subcommand_matches("shell-hook") {
+        process_shell_hook_command(matches, api_key, profile_name)?;
+        process::exit(0)
+    }
+
     // check for updates based on the configuration (if any)
     if let Some(updates) = Config::load_updates()? {
         check_updates(&updates)?;
@@ -224,6 +242,11 @@ pub fn main() -> Result<()> {
         process::exit(0)
     }
 
+    if let Some(matches) = matches.subcommand_matches("grants") {
+        process_grants_command(matches, &rest_cfg)?;
+        process::exit(0);
+    }
+
     if let Some(matches) = matches.subcommand_matches("groups") {
         process_groups_command(matches, &rest_cfg)?;
         process::exit(0);
@@ -276,21 +299,25 @@ pub fn main() -> Result<()> {
 
     //====================================================
     // Everything below here requires resolved environment/project values
-    let resolver = Resolver::new();
     let env_name = config.environment.as_deref().unwrap_or(DEFAULT_ENV_NAME);
     let proj_name = config.project.as_deref().unwrap_or_default();
-    let resolved = resolver.resolve_ids(&rest_cfg, proj_name, env_name)?;
+    let cache = OfflineCache::new(config);
+    let resolved = cached_resolve_ids(cache.as_ref(), &rest_cfg, proj_name, env_name)?;
 
     if let Some(matches) = matches.subcommand_matches("parameters") {
-        process_parameters_command(matches, &rest_cfg, &resolved)?;
+        process_parameters_command(matches, &rest_cfg, &resolved, config)?;
     }
 
     if let Some(matches) = matches.subcommand_matches("templates") {
-        process_templates_command(matches, &rest_cfg, &resolved)?;
+        process_templates_command(matches, &rest_cfg, &resolved, config)?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lock") {
+        process_lock_command(matches, &rest_cfg, &resolved, config)?;
     }
 
     if let Some(matches) = matches.subcommand_matches("run") {
-        process_run_command(matches, &rest_cfg, &resolved)?;
+        process_run_command(matches, &rest_cfg, &resolved, config)?;
     }
 
     Ok(())
@@ -0,0 +1,256 @@
+use crate::cli::AS_OF_ARG;
+use crate::database::{OpenApiConfig, ResolvedDetails};
+use crate::key_transform::KeyTransform;
+use crate::run::get_run_values;
+use crate::subprocess::{changed_keys, EnvSettings};
+use crate::utils::{
+    current_time, encryption_key, error_message, parse_datetime, parse_tag, warn_unresolved_params,
+};
+use clap::ArgMatches;
+use cloudtruth_config::Config;
+use color_eyre::eyre::Result;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::process;
+
+pub const DEFAULT_LOCK_FILE: &str = "cloudtruth.lock";
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum LockError {
+    Read(String, String),
+    Invalid(String, String),
+    UnsupportedVersion(String, u32),
+}
+
+impl error::Error for LockError {}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            LockError::Read(path, e) => write!(f, "Failed to read lock file '{path}': {e}"),
+            LockError::Invalid(path, e) => write!(f, "Invalid lock file '{path}': {e}"),
+            LockError::UnsupportedVersion(path, version) => write!(
+                f,
+                "Lock file '{path}' has unsupported version {version} (expected {LOCK_VERSION})"
+            ),
+        }
+    }
+}
+
+/// The parameter values of a project/environment at a point in time, recorded as salted hashes so
+/// the lock file can be committed without exposing the values.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockFile {
+    pub version: u32,
+    pub project: String,
+    pub environment: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<String>,
+    salt: String,
+    pub parameters: BTreeMap<String, String>,
+}
+
+fn hash_value(salt: &str, value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(value.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+impl LockFile {
+    /// Records the `values` that were retrieved at `as_of` (or for the `tag`).
+    pub fn new(
+        resolved: &ResolvedDetails,
+        as_of: Option<String>,
+        tag: Option<String>,
+        values: &EnvSettings,
+    ) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let salt = hex::encode(salt);
+        Self {
+            version: LOCK_VERSION,
+            project: resolved.project_display_name().to_string(),
+            environment: resolved.environment_display_name().to_string(),
+            tag,
+            as_of,
+            parameters: values
+                .iter()
+                .map(|(k, v)| (k.clone(), hash_value(&salt, v)))
+                .collect(),
+            salt,
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, LockError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| LockError::Read(path.to_string(), e.to_string()))?;
+        let lock: Self = serde_json::from_str(&text)
+            .map_err(|e| LockError::Invalid(path.to_string(), e.to_string()))?;
+        if lock.version != LOCK_VERSION {
+            return Err(LockError::UnsupportedVersion(
+                path.to_string(),
+                lock.version,
+            ));
+        }
+        Ok(lock)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, format!("{}\n", serde_json::to_string_pretty(self)?))?;
+        Ok(())
+    }
+
+    /// Lists the names that were added, removed or have different values than when locked. The
+    /// `values` have the key `transform` applied, so it is applied to the locked names too.
+    pub fn mismatched_keys(&self, values: &EnvSettings, transform: &KeyTransform) -> Vec<String> {
+        let locked: BTreeMap<String, String> = self
+            .parameters
+            .iter()
+            .map(|(k, v)| (transform.transform_key(k), v.clone()))
+            .collect();
+        let current: BTreeMap<String, String> = values
+            .iter()
+            .map(|(k, v)| (k.clone(), hash_value(&self.salt, v)))
+            .collect();
+        changed_keys(&locked, &current)
+    }
+}
+
+/// Loads the lock file given by `--locked`, and makes sure it is for the resolved project and
+/// environment.
+pub fn load_lock_file(path: &str, resolved: &ResolvedDetails) -> LockFile {
+    let lock = match LockFile::load(path) {
+        Ok(lock) => lock,
+        Err(e) => {
+            error_message(e.to_string());
+            process::exit(76);
+        }
+    };
+    if lock.project != resolved.project_display_name()
+        || lock.environment != resolved.environment_display_name()
+    {
+        error_message(format!(
+            "Lock file '{}' is for project '{}' environment '{}', not project '{}' environment '{}'",
+            path,
+            lock.project,
+            lock.environment,
+            resolved.project_display_name(),
+            resolved.environment_display_name()
+        ));
+        process::exit(77);
+    }
+    lock
+}
+
+/// Refuses to continue when the `values` do not match the lock file.
+pub fn verify_locked_values(
+    lock: &LockFile,
+    path: &str,
+    values: &EnvSettings,
+    transform: &KeyTransform,
+) {
+    let mismatched = lock.mismatched_keys(values, transform);
+    if !mismatched.is_empty() {
+        error_message(format!(
+            "Parameter values do not match lock file '{}': {}",
+            path,
+            mismatched.join(", ")
+        ));
+        process::exit(77);
+    }
+}
+
+/// Process the 'lock' sub-command
+pub fn process_lock_command(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    resolved: &ResolvedDetails,
+    config: &Config,
+) -> Result<()> {
+    let path = subcmd_args.value_of("output").unwrap_or(DEFAULT_LOCK_FILE);
+    let tag = parse_tag(subcmd_args.value_of(AS_OF_ARG));
+    // without a tag, the values are fetched at a fixed time that is recorded, so the same values
+    // can be retrieved later
+    let as_of = match tag {
+        Some(_) => None,
+        None => parse_datetime(subcmd_args.value_of(AS_OF_ARG)).or_else(|| Some(current_time())),
+    };
+    let key_material = encryption_key(config.encryption_key_file.as_deref());
+    let run_values = get_run_values(
+        rest_cfg,
+        resolved,
+        as_of.clone(),
+        tag.clone(),
+        key_material.as_deref(),
+    )?;
+    warn_unresolved_params(&run_values.errors);
+    let lock = LockFile::new(resolved, as_of, tag, &run_values.values);
+    lock.save(path)?;
+    println!(
+        "Locked {} parameters from project '{}' environment '{}' in '{}'.",
+        lock.parameters.len(),
+        lock.project,
+        lock.environment,
+        path
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_round_trip() {
+        let resolved = ResolvedDetails::new(
+            "default".to_string(),
+            "env-id".to_string(),
+            "proj".to_string(),
+            "proj-id".to_string(),
+        );
+        let mut values = EnvSettings::new();
+        values.insert("db.host".to_string(), "localhost".to_string());
+        values.insert("password".to_string(), "hunter22".to_string());
+        let as_of = Some(current_time());
+        let lock = LockFile::new(&resolved, as_of.clone(), None, &values);
+        assert_eq!(lock.as_of, as_of);
+        assert_eq!(lock.project, "proj");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_LOCK_FILE);
+        let path = path.to_str().unwrap();
+        lock.save(path).unwrap();
+        assert!(!fs::read_to_string(path).unwrap().contains("hunter22"));
+        let loaded = LockFile::load(path).unwrap();
+        assert_eq!(loaded, lock);
+
+        let transform = KeyTransform::default();
+        assert!(loaded.mismatched_keys(&values, &transform).is_empty());
+        let mut changed = values.clone();
+        changed.insert("password".to_string(), "hunter23".to_string());
+        changed.insert("extra".to_string(), "value".to_string());
+        assert_eq!(
+            loaded.mismatched_keys(&changed, &transform),
+            vec!["extra".to_string(), "password".to_string()]
+        );
+
+        // names are compared after the key transforms
+        let transform = KeyTransform {
+            uppercase: true,
+            sanitize: true,
+            ..Default::default()
+        };
+        let renamed = transform.apply(values).unwrap();
+        assert!(loaded.mismatched_keys(&renamed, &transform).is_empty());
+    }
+}
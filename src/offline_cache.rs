@@ -0,0 +1,278 @@
+use crate::database::{
+    CryptoAlgorithm, CryptoEnvelope, CryptoKdf, OpenApiConfig, ResolveError, ResolvedDetails,
+    Resolver,
+};
+use crate::utils::warning_message;
+use chrono::{DateTime, Duration, Utc};
+use cloudtruth_config::Config;
+use color_eyre::eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Encrypted local copies of what the server returned, used when the server cannot be reached.
+///
+/// Entries are keyed by the profile and the request (project, environment, tag, ...), and are
+/// encrypted with a key derived from the profile API key.
+pub struct OfflineCache {
+    dir: PathBuf,
+    profile: String,
+    key_material: Vec<u8>,
+    ttl: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: String,
+    envelope: CryptoEnvelope,
+}
+
+impl OfflineCache {
+    /// Returns `None` unless the offline cache is enabled for the profile.
+    pub fn new(config: &Config) -> Option<Self> {
+        if !config.offline_cache || config.api_key.is_empty() {
+            return None;
+        }
+        Some(Self {
+            dir: Config::cache_dir()?,
+            profile: config.profile_name.clone(),
+            key_material: config.api_key.as_bytes().to_vec(),
+            ttl: Duration::from_std(config.offline_cache_ttl).unwrap_or_else(|_| Duration::zero()),
+        })
+    }
+
+    fn path(&self, key: &[&str]) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(self.profile.as_bytes());
+        for part in key {
+            hasher.update(b"\0");
+            hasher.update(part.as_bytes());
+        }
+        self.dir
+            .join(format!("{}.json", hex::encode(hasher.finalize())))
+    }
+
+    fn write(&self, key: &[&str], plaintext: &[u8]) -> Result<()> {
+        let entry = CacheEntry {
+            created_at: Utc::now().to_rfc3339(),
+            envelope: CryptoEnvelope::encrypt(
+                &CryptoAlgorithm::AesGcm,
+                &CryptoKdf::HkdfSha256,
+                &self.key_material,
+                plaintext,
+            )?,
+        };
+        fs::create_dir_all(&self.dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.dir, fs::Permissions::from_mode(0o700))?;
+        }
+        // the temporary file is only readable by the current user, and the rename means readers
+        // never see a partial entry
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(serde_json::to_string(&entry)?.as_bytes())?;
+        file.persist(self.path(key))?;
+        Ok(())
+    }
+
+    /// Saves the `value`. Failures are only reported, since the cache is a convenience.
+    pub fn store<T: Serialize>(&self, key: &[&str], value: &T) {
+        let result = serde_json::to_vec(value)
+            .map_err(|e| e.into())
+            .and_then(|plaintext| self.write(key, &plaintext));
+        if let Err(e) = result {
+            warning_message(format!("Failed to update the offline cache: {e}"));
+        }
+    }
+
+    /// Gets the saved value, along with when it was saved. Expired entries are removed.
+    pub fn load<T: DeserializeOwned>(&self, key: &[&str]) -> Option<(T, DateTime<Utc>)> {
+        let path = self.path(key);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+        let created_at = DateTime::parse_from_rfc3339(&entry.created_at)
+            .ok()?
+            .with_timezone(&Utc);
+        if created_at + self.ttl < Utc::now() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        let plaintext = entry.envelope.decrypt(&self.key_material).ok()?;
+        let value = serde_json::from_slice(&plaintext).ok()?;
+        Some((value, created_at))
+    }
+
+    /// Gets the saved value (with a warning) when the request `error` means the server could
+    /// not be reached.
+    fn fallback<T: DeserializeOwned>(&self, key: &[&str], error: &dyn Display) -> Option<T> {
+        let message = error.to_string();
+        if !is_unavailable(&message) {
+            return None;
+        }
+        let (value, created_at) = self.load(key)?;
+        warning_message(format!(
+            "Using offline cache values from {}, since the request failed: {}",
+            created_at.to_rfc3339(),
+            message
+        ));
+        Some(value)
+    }
+}
+
+/// Whether the request failed because the server could not be reached (e.g. connection, DNS or
+/// timeout problems) or had an internal error. Other failures (e.g. authentication or permission
+/// errors) are not hidden by the offline cache.
+fn is_unavailable(message: &str) -> bool {
+    if message.contains("error in reqwest:") || message.contains("error in response: status code 5")
+    {
+        return true;
+    }
+    // server responses are reported as "<reason> (<status>): <details>"
+    message.split('(').skip(1).any(|part| {
+        let bytes = part.as_bytes();
+        bytes.len() >= 5
+            && bytes[0] == b'5'
+            && bytes[1..3].iter().all(|b| b.is_ascii_digit())
+            && part[3..].starts_with("):")
+    })
+}
+
+/// Gets the value from the server and caches it. When the server cannot be reached, the cached
+/// value is used (with a warning) instead of failing.
+pub fn cached_fetch<T, E, F>(cache: Option<&OfflineCache>, key: &[&str], fetch: F) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+    E: Display,
+    F: FnOnce() -> Result<T, E>,
+{
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return fetch(),
+    };
+    match fetch() {
+        Ok(value) => {
+            cache.store(key, &value);
+            Ok(value)
+        }
+        Err(e) => cache.fallback(key, &e).ok_or(e),
+    }
+}
+
+/// Resolves the project and environment names, using cached IDs when the server cannot be
+/// reached. Names that the server does not know are never resolved from the cache.
+pub fn cached_resolve_ids(
+    cache: Option<&OfflineCache>,
+    rest_cfg: &OpenApiConfig,
+    proj_name: &str,
+    env_name: &str,
+) -> Result<ResolvedDetails, ResolveError> {
+    let resolver = Resolver::new();
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return resolver.resolve_ids(rest_cfg, proj_name, env_name),
+    };
+    let key = ["resolve", proj_name, env_name];
+    match resolver.resolve_ids(rest_cfg, proj_name, env_name) {
+        Ok(resolved) => {
+            cache.store(&key, &resolved);
+            Ok(resolved)
+        }
+        Err(ResolveError::ResolutionNotFound(errors)) => {
+            Err(ResolveError::ResolutionNotFound(errors))
+        }
+        Err(e) => cache.fallback(&key, &e).ok_or(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn test_cache(dir: &tempfile::TempDir, ttl: i64) -> OfflineCache {
+        OfflineCache {
+            dir: dir.path().join("cache"),
+            profile: "default".to_string(),
+            key_material: b"api-key".to_vec(),
+            ttl: Duration::seconds(ttl),
+        }
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = test_cache(&dir, 60);
+        let mut values = HashMap::new();
+        values.insert("KEY".to_string(), "secret-value".to_string());
+        cache.store(&["run", "proj", "env", ""], &values);
+
+        let (loaded, _): (HashMap<String, String>, _) =
+            cache.load(&["run", "proj", "env", ""]).unwrap();
+        assert_eq!(loaded, values);
+        assert!(cache
+            .load::<HashMap<String, String>>(&["run", "proj", "env", "tag"])
+            .is_none());
+
+        // the value is not stored in plaintext
+        let path = cache.path(&["run", "proj", "env", ""]);
+        assert!(!fs::read_to_string(path).unwrap().contains("secret-value"));
+
+        // another API key cannot read it
+        let other = OfflineCache {
+            key_material: b"other-key".to_vec(),
+            ..test_cache(&dir, 60)
+        };
+        assert!(other
+            .load::<HashMap<String, String>>(&["run", "proj", "env", ""])
+            .is_none());
+    }
+
+    #[test]
+    fn cache_fallback_and_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = test_cache(&dir, 60);
+        let key = ["export", "proj"];
+        let result: Result<String, String> =
+            cached_fetch(Some(&cache), &key, || Ok("fresh".to_string()));
+        assert_eq!(result.unwrap(), "fresh");
+        let timed_out = "Unhandled error: error in reqwest: operation timed out".to_string();
+        let result: Result<String, String> =
+            cached_fetch(Some(&cache), &key, || Err(timed_out.clone()));
+        assert_eq!(result.unwrap(), "fresh");
+
+        // only used when the server cannot be reached
+        let denied = "Forbidden (403): You do not have permission".to_string();
+        let result: Result<String, String> =
+            cached_fetch(Some(&cache), &key, || Err(denied.clone()));
+        assert_eq!(result.unwrap_err(), denied);
+
+        let expired = test_cache(&dir, -1);
+        let result: Result<String, String> =
+            cached_fetch(Some(&expired), &key, || Err(timed_out.clone()));
+        assert_eq!(result.unwrap_err(), timed_out);
+        assert!(!cache.path(&key).exists());
+    }
+
+    #[test]
+    fn unavailable_errors() {
+        assert!(is_unavailable(
+            "Unhandled error: error in reqwest: error trying to connect: dns error"
+        ));
+        assert!(is_unavailable(
+            "Service Unavailable (503): No details available"
+        ));
+        assert!(is_unavailable(
+            "Unhandled error: error in response: status code 502 Bad Gateway"
+        ));
+        assert!(!is_unavailable("Not Authenticated: Invalid API key"));
+        assert!(!is_unavailable("Unauthorized (401): Invalid token"));
+        assert!(!is_unavailable(
+            "Forbidden (403): Permission denied (500 max)"
+        ));
+        assert!(!is_unavailable("Did not find environment 'prod'"));
+    }
+}
@@ -2,34 +2,47 @@ use crate::binary_name;
 use crate::cli::{
     show_values, true_false_option, AS_OF_ARG, CONFIRM_FLAG, COPY_DEST_NAME_ARG, COPY_SRC_NAME_ARG,
     COPY_SUBCMD, DELETE_SUBCMD, DESCRIPTION_OPT, DIFF_SUBCMD, FORMAT_OPT, GET_SUBCMD,
-    IMMEDIATE_PARAMETERS_FLAG, JMES_PATH_ARG, KEY_ARG, LIST_SUBCMD, PUSH_SUBCMD, RENAME_OPT,
-    RULE_MAX_ARG, RULE_MAX_LEN_ARG, RULE_MIN_ARG, RULE_MIN_LEN_ARG, RULE_NO_MAX_ARG,
+    HISTORY_SUBCMD, IMMEDIATE_PARAMETERS_FLAG, JMES_PATH_ARG, KEY_ARG, LIST_SUBCMD, PUSH_SUBCMD,
+    RENAME_OPT, RULE_MAX_ARG, RULE_MAX_LEN_ARG, RULE_MIN_ARG, RULE_MIN_LEN_ARG, RULE_NO_MAX_ARG,
     RULE_NO_MAX_LEN_ARG, RULE_NO_MIN_ARG, RULE_NO_MIN_LEN_ARG, RULE_NO_REGEX_ARG, RULE_REGEX_ARG,
-    SECRETS_FLAG, SET_SUBCMD, SHOW_TIMES_FLAG,
+    SECRETS_FLAG, SET_SUBCMD, SHOW_TIMES_FLAG, VALUES_FLAG,
 };
 use crate::database::{
-    EnvironmentDetails, Environments, OpenApiConfig, ParamExportFormat, ParamExportOptions,
-    ParamRuleType, ParameterDetails, ParameterError, Parameters, Projects, ResolvedDetails,
-    TaskStepDetails,
+    env_export_name, format_export_values, format_github_actions, last_from_url,
+    resolve_base_types, CryptoAlgorithm, CryptoEnvelope, CryptoKdf, EnvironmentDetails,
+    Environments, HistoryAction, KubernetesExport, OpenApiConfig, ParamExportFormat,
+    ParamExportOptions, ParamRuleType, ParameterDetails, ParameterError, ParameterHistory,
+    Parameters, Projects, ResolvedDetails, TaskStepDetails, Types,
 };
+use crate::key_transform::KeyTransform;
+use crate::lock::{load_lock_file, verify_locked_values};
+use crate::offline_cache::{cached_fetch, OfflineCache};
+use crate::run::get_run_values;
 use crate::subprocess::EnvSettings;
 use crate::table::Table;
 use crate::utils::{
-    error_message, format_param_error, help_message, parse_datetime, parse_tag, user_confirm,
-    warn_missing_subcommand, warn_unresolved_params, warning_message, DEL_CONFIRM, FILE_READ_ERR,
-    REDACTED,
+    decrypt_value, encryption_key, error_message, error_no_environment_message, format_param_error,
+    help_message, parse_datetime, parse_tag, user_confirm, warn_missing_subcommand,
+    warn_unresolved_params, warning_message, DEL_CONFIRM, FILE_READ_ERR, REDACTED,
 };
 use clap::ArgMatches;
-use cloudtruth_config::DEFAULT_ENV_NAME;
+use cloudtruth_config::{Config, DEFAULT_ENV_NAME};
 use color_eyre::eyre::Result;
 use color_eyre::Report;
 use indoc::printdoc;
 use rpassword::read_password;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::fs;
+use std::io::Write;
 use std::process;
 use std::str::FromStr;
 
+const RESTORE_CREATE: &str = "create";
+const RESTORE_UPDATE: &str = "update";
+const RESTORE_UNSET: &str = "unset";
+const RESTORE_UNCHANGED: &str = "unchanged";
+
 fn proc_param_delete(
     subcmd_args: &ArgMatches,
     rest_cfg: &OpenApiConfig,
@@ -435,6 +448,7 @@ fn proc_param_export(
     rest_cfg: &OpenApiConfig,
     parameters: &Parameters,
     resolved: &ResolvedDetails,
+    config: &Config,
 ) -> Result<()> {
     let proj_id = resolved.project_id();
     let env_id = resolved.environment_id();
@@ -442,8 +456,26 @@ fn proc_param_export(
     let ends_with = subcmd_args.value_of("ends_with");
     let contains = subcmd_args.value_of("contains");
     let template_format = subcmd_args.value_of("FORMAT").unwrap();
-    let as_of = parse_datetime(subcmd_args.value_of(AS_OF_ARG));
-    let tag = parse_tag(subcmd_args.value_of(AS_OF_ARG));
+    let key_material = encryption_key(config.encryption_key_file.as_deref());
+    let (as_of, tag) = match subcmd_args.value_of("locked") {
+        Some(path) => {
+            // all the parameters are verified, even those the filters leave out of the export
+            let lock = load_lock_file(path, resolved);
+            let run_values = get_run_values(
+                rest_cfg,
+                resolved,
+                lock.as_of.clone(),
+                lock.tag.clone(),
+                key_material.as_deref(),
+            )?;
+            verify_locked_values(&lock, path, &run_values.values, &KeyTransform::default());
+            (lock.as_of, lock.tag)
+        }
+        None => (
+            parse_datetime(subcmd_args.value_of(AS_OF_ARG)),
+            parse_tag(subcmd_args.value_of(AS_OF_ARG)),
+        ),
+    };
     let export = subcmd_args.is_present("export");
     let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
     let options = ParamExportOptions {
@@ -453,10 +485,140 @@ fn proc_param_export(
         contains: contains.map(|s| s.to_string()),
         export: Some(export),
         secrets: Some(show_secrets),
-        as_of,
-        tag,
+        as_of: as_of.clone(),
+        tag: tag.clone(),
+        delimiter: subcmd_args.value_of("delimiter").map(String::from),
+        map_name: subcmd_args.value_of("name").map(String::from),
     };
-    let body = parameters.export_parameters(rest_cfg, proj_id, env_id, options)?;
+    let format = ParamExportFormat::from_str(template_format).unwrap();
+    let transform = KeyTransform::new(subcmd_args, config);
+    let cache = OfflineCache::new(config);
+    // values are decrypted before they are formatted, so the formats that the server usually
+    // builds are built here when there is an encryption key
+    if format.is_client_side() || key_material.is_some() {
+        let key = [
+            "export-values",
+            proj_id,
+            env_id,
+            starts_with.unwrap_or_default(),
+            ends_with.unwrap_or_default(),
+            contains.unwrap_or_default(),
+            &show_secrets.to_string(),
+            as_of.as_deref().unwrap_or_default(),
+            tag.as_deref().unwrap_or_default(),
+        ];
+        let entries = cached_fetch(cache.as_ref(), &key, || {
+            parameters.export_values(rest_cfg, proj_id, env_id, &options)
+        })?;
+        let mut values = EnvSettings::new();
+        let mut secrets = BTreeSet::new();
+        let mut types = BTreeMap::new();
+        let mut errors: Vec<String> = vec![];
+        for (k, v) in entries {
+            let name = if format.is_client_side() {
+                k.clone()
+            } else {
+                match env_export_name(&k) {
+                    Some(name) => name,
+                    None => continue,
+                }
+            };
+            values.insert(
+                name.clone(),
+                decrypt_value(&k, &v.value, key_material.as_deref())?,
+            );
+            if v.secret {
+                secrets.insert(transform.transform_key(&name));
+            }
+            types.insert(transform.transform_key(&name), v.param_type);
+            if !v.error.is_empty() {
+                errors.push(format_param_error(&k, &v.error));
+            }
+        }
+        warn_unresolved_params(&errors);
+        let values = transform.apply(values)?.into_iter().collect();
+        let builtin = ["string", "integer", "boolean"];
+        if matches!(format, ParamExportFormat::Tfvars)
+            && types.values().any(|t| !builtin.contains(&t.as_str()))
+        {
+            // custom types are unquoted like the integer or boolean type they derive from
+            match Types::new().get_type_details(rest_cfg) {
+                Ok(details) => {
+                    let parents = details
+                        .into_iter()
+                        .map(|d| (d.name, d.parent_name))
+                        .collect();
+                    resolve_base_types(&mut types, &parents);
+                }
+                Err(e) => warning_message(format!(
+                    "Failed to get the parameter types, so custom type values are quoted: {e}"
+                )),
+            }
+        }
+        let output = match format {
+            ParamExportFormat::Kubernetes => {
+                let export = KubernetesExport {
+                    name: subcmd_args.value_of("name").unwrap().to_string(),
+                    namespace: subcmd_args.value_of("namespace").map(String::from),
+                    list: subcmd_args.is_present("list"),
+                    project: resolved.project_display_name().to_string(),
+                    environment: resolved.environment_display_name().to_string(),
+                    tag: tag.clone(),
+                };
+                export.format(&values, &secrets)?
+            }
+            ParamExportFormat::GithubActions => {
+                let (commands, env_file) = format_github_actions(&values, &secrets);
+                match env::var("GITHUB_ENV") {
+                    Ok(path) if !path.is_empty() => {
+                        let mut file = fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(path)?;
+                        file.write_all(env_file.as_bytes())?;
+                        print!("{commands}");
+                        return Ok(());
+                    }
+                    _ => {
+                        warning_message(
+                            "GITHUB_ENV is not set, so the variables are printed instead",
+                        );
+                        commands + &env_file
+                    }
+                }
+            }
+            ParamExportFormat::Gitlab if !secrets.is_empty() => {
+                warning_message(
+                    "GitLab does not mask dotenv artifact variables, so the secret values are visible in the artifact",
+                );
+                format_export_values(&values, &types, &secrets, &options)?
+            }
+            _ => format_export_values(&values, &types, &secrets, &options)?,
+        };
+        println!("{}", output.trim_end());
+        return Ok(());
+    }
+
+    let key = [
+        "export",
+        proj_id,
+        env_id,
+        template_format,
+        starts_with.unwrap_or_default(),
+        ends_with.unwrap_or_default(),
+        contains.unwrap_or_default(),
+        &export.to_string(),
+        &show_secrets.to_string(),
+        as_of.as_deref().unwrap_or_default(),
+        tag.as_deref().unwrap_or_default(),
+    ];
+    let mut body = cached_fetch(cache.as_ref(), &key, || {
+        parameters.export_parameters(rest_cfg, proj_id, env_id, options)
+    })?;
+    if let Some(text) = &body {
+        let quoted = !matches!(format, ParamExportFormat::Docker);
+        body = Some(transform.apply_to_export(text, quoted)?);
+    }
 
     if let Some(body) = body {
         println!("{body}")
@@ -476,6 +638,7 @@ fn proc_param_get(
     rest_cfg: &OpenApiConfig,
     parameters: &Parameters,
     resolved: &ResolvedDetails,
+    config: &Config,
 ) -> Result<()> {
     let key = subcmd_args.value_of(KEY_ARG).unwrap();
     let show_details = subcmd_args.is_present("details");
@@ -498,7 +661,10 @@ fn proc_param_get(
         tag,
     )?;
 
-    if let Some(param) = parameter {
+    if let Some(mut param) = parameter {
+        let key_material = encryption_key(config.encryption_key_file.as_deref());
+        param.value = decrypt_value(key, &param.value, key_material.as_deref())?;
+
         // Treat parameters without values set as if the value were simply empty, since
         // we need to display something sensible to the user.
         if !show_details {
@@ -822,6 +988,8 @@ fn proc_param_set(
     let immediate_parameters = false;
     let param_type = subcmd_args.value_of("param-type");
     let create_child = subcmd_args.is_present("create-child");
+    let encrypt_with = subcmd_args.value_of("encrypt-with");
+    let encrypted: String;
 
     // make sure the user did not over-specify
     let mut specified: Vec<bool> = vec![
@@ -857,6 +1025,27 @@ fn proc_param_set(
         value = Some(val_str.as_str());
     }
 
+    // encrypt locally, so the server only ever sees the envelope
+    if let Some(key_file) = encrypt_with {
+        if secret != Some(true) {
+            error_message("Client-side encryption requires '--secret true'");
+            process::exit(62);
+        }
+        let Some(plaintext) = value else {
+            error_message("Client-side encryption requires a value to encrypt");
+            process::exit(63);
+        };
+        let key_material = fs::read(key_file).expect(FILE_READ_ERR);
+        let envelope = CryptoEnvelope::encrypt(
+            &CryptoAlgorithm::AesGcm,
+            &CryptoKdf::HkdfSha256,
+            &key_material,
+            plaintext.as_bytes(),
+        )?;
+        encrypted = envelope.to_value();
+        value = Some(encrypted.as_str());
+    }
+
     let param_field_update =
         description.is_some() || secret.is_some() || param_type.is_some() || rename.is_some();
     let value_field_update =
@@ -1309,17 +1498,469 @@ fn proc_param_drift(
     Ok(())
 }
 
+fn proc_param_history(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    parameters: &Parameters,
+    resolved: &ResolvedDetails,
+) -> Result<()> {
+    let proj_name = resolved.project_display_name();
+    let proj_id = resolved.project_id();
+    let env_id = resolved.environment_id();
+    let key_name = subcmd_args.value_of(KEY_ARG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let show_values = subcmd_args.is_present(VALUES_FLAG);
+    let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
+    let as_list: Vec<&str> = subcmd_args
+        .values_of(AS_OF_ARG)
+        .unwrap_or_default()
+        .collect();
+    let max_len: usize = 2;
+    if as_list.len() > max_len {
+        warning_message(format!("Can specify a maximum of {max_len} as-of values."));
+        return Ok(());
+    }
+
+    // with two values, the earlier date/time is the start of the window
+    let mut start: Option<String> = None;
+    let mut end = as_list.first().copied();
+    if as_list.len() == 2 {
+        let first = parse_datetime(Some(as_list[0]));
+        let second = parse_datetime(Some(as_list[1]));
+        match (first, second) {
+            (Some(a), Some(b)) if a > b => {
+                start = Some(b);
+                end = Some(as_list[0]);
+            }
+            (Some(a), _) => {
+                start = Some(a);
+                end = Some(as_list[1]);
+            }
+            (None, Some(b)) => start = Some(b),
+            (None, None) => {
+                error_message("The start of a history window must be a date/time, not a tag.");
+                process::exit(64);
+            }
+        }
+    }
+    let as_of = parse_datetime(end);
+    let tag = parse_tag(end);
+
+    let modifier;
+    let add_name;
+    let mut secrets: HashMap<String, bool> = HashMap::new();
+    let mut history: Vec<ParameterHistory>;
+    if let Some(param_name) = key_name {
+        if let Some(details) = parameters.get_details_by_name(
+            rest_cfg, proj_id, env_id, param_name, false, false, true, None, None,
+        )? {
+            secrets.insert(details.key.clone(), details.secret);
+            history = parameters.get_history_for(rest_cfg, proj_id, &details.id, as_of, tag)?;
+            modifier = format!("for '{param_name}' ");
+            add_name = false;
+        } else {
+            error_message(format!(
+                "Did not find parameter '{param_name}' from project '{proj_name}'.",
+            ));
+            process::exit(44);
+        }
+    } else {
+        history = parameters.get_histories(rest_cfg, proj_id, as_of, tag)?;
+        modifier = "".to_string();
+        add_name = true;
+        if show_values {
+            for details in parameters
+                .get_parameter_details(rest_cfg, proj_id, env_id, false, true, false, None, None)?
+            {
+                secrets.insert(details.key.clone(), details.secret);
+            }
+        }
+    }
+    if let Some(start) = &start {
+        history.retain(|h| &h.modified_at >= start);
+    }
+
+    if history.is_empty() {
+        println!("No parameter history {modifier}in project '{proj_name}'.");
+    } else {
+        let name_index = 4;
+        let mut table = Table::new("parameter-history");
+        let mut hdr: Vec<&str> = vec!["Date", "User", "Action", "Type", "Environment"];
+        let mut properties = vec![
+            "modified_at",
+            "user_name",
+            "action",
+            "model",
+            "environments",
+        ];
+        if add_name {
+            hdr.insert(name_index, "Name");
+            properties.insert(name_index, "name");
+        }
+        if show_values {
+            hdr.push("Value");
+        }
+        table.set_header(&hdr);
+
+        // the values for all the changes at the same time are fetched together
+        let mut values = HistoryValues::new();
+        if show_values {
+            for modified_at in history_value_times(&history) {
+                for details in parameters.get_environment_details(
+                    rest_cfg,
+                    proj_id,
+                    key_name,
+                    false,
+                    !show_secrets,
+                    false,
+                    Some(modified_at.clone()),
+                )? {
+                    let env_id = last_from_url(&details.env_url).to_string();
+                    values.insert((modified_at.clone(), details.key.clone(), env_id), details);
+                }
+            }
+        }
+        for entry in &history {
+            let mut row: Vec<String> = properties.iter().map(|p| entry.get_property(p)).collect();
+            if show_values {
+                row.push(history_values(entry, &values, &secrets, show_secrets));
+            }
+            table.add_row(row);
+        }
+        table.render(fmt)?;
+    }
+    Ok(())
+}
+
+/// The parameter values at the time of each change, by (time, parameter name, environment id).
+type HistoryValues = HashMap<(String, String, String), ParameterDetails>;
+
+/// The times of the history changes that set a value.
+fn history_value_times(history: &[ParameterHistory]) -> BTreeSet<String> {
+    history
+        .iter()
+        .filter(|h| h.model == "value" && h.change_type != HistoryAction::Delete)
+        .filter(|h| !h.environments.is_empty())
+        .map(|h| h.modified_at.clone())
+        .collect()
+}
+
+/// Shows the value set by the history `entry` in each environment.
+fn history_values(
+    entry: &ParameterHistory,
+    values: &HistoryValues,
+    secrets: &HashMap<String, bool>,
+    show_secrets: bool,
+) -> String {
+    if entry.model != "value" || entry.change_type == HistoryAction::Delete {
+        return "".to_string();
+    }
+    let mut result: Vec<String> = vec![];
+    for (env_name, env_id) in entry.environments.iter().zip(&entry.environment_ids) {
+        let key = (
+            entry.modified_at.clone(),
+            entry.name.clone(),
+            env_id.clone(),
+        );
+        let value = match values.get(&key) {
+            Some(d) if d.secret && !show_secrets => REDACTED.to_string(),
+            Some(d) => d.value.clone(),
+            None if secrets.get(&entry.name) == Some(&true) && !show_secrets => {
+                REDACTED.to_string()
+            }
+            None => "".to_string(),
+        };
+        if entry.environments.len() == 1 {
+            result.push(value);
+        } else {
+            result.push(format!("{env_name}: {value}"));
+        }
+    }
+    result.join("\n")
+}
+
+/// A parameter value that differs from its value at an earlier time.
+struct ValueRestore {
+    key: String,
+    env_name: String,
+    env_id: String,
+    action: &'static str,
+    before: String,
+    after: String,
+    current: ParameterDetails,
+    /// The earlier value, when it was set directly in the environment.
+    previous: Option<ParameterDetails>,
+}
+
+/// Returns the `details` when the value is set directly in the environment (not inherited).
+fn direct_value(details: Option<&ParameterDetails>, env_id: &str) -> Option<ParameterDetails> {
+    details
+        .filter(|d| !d.val_id.is_empty() && d.env_url.contains(env_id))
+        .cloned()
+}
+
+fn restore_display(details: Option<&ParameterDetails>, secret: bool, show_secrets: bool) -> String {
+    match details {
+        None => "".to_string(),
+        Some(d) if d.external && d.jmes_path.is_empty() => d.fqn.clone(),
+        Some(d) if d.external => format!("{} ({})", d.fqn, d.jmes_path),
+        Some(_) if secret && !show_secrets => REDACTED.to_string(),
+        Some(d) => d.raw_value.clone(),
+    }
+}
+
+/// Compares the `current` and `previous` values of a parameter in an environment.
+///
+/// A value that was inherited (or did not exist) at the earlier time gets unset.
+fn plan_value_restore(
+    env_name: &str,
+    env_id: &str,
+    current: &ParameterDetails,
+    previous: Option<&ParameterDetails>,
+    show_secrets: bool,
+) -> ValueRestore {
+    let was = direct_value(previous, env_id);
+    let now = direct_value(Some(current), env_id);
+    let action = match (&was, &now) {
+        (Some(w), Some(n)) => {
+            let same = w.external == n.external
+                && w.fqn == n.fqn
+                && w.jmes_path == n.jmes_path
+                && w.raw_value == n.raw_value
+                && w.evaluated == n.evaluated;
+            match same {
+                true => RESTORE_UNCHANGED,
+                false => RESTORE_UPDATE,
+            }
+        }
+        (Some(_), None) => RESTORE_CREATE,
+        (None, Some(_)) => RESTORE_UNSET,
+        (None, None) => RESTORE_UNCHANGED,
+    };
+    let secret = current.secret || previous.map(|p| p.secret).unwrap_or_default();
+    ValueRestore {
+        key: current.key.clone(),
+        env_name: env_name.to_string(),
+        env_id: env_id.to_string(),
+        action,
+        before: restore_display(now.as_ref(), secret, show_secrets),
+        after: restore_display(was.as_ref(), secret, show_secrets),
+        current: current.clone(),
+        previous: was,
+    }
+}
+
+fn proc_param_restore(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    parameters: &Parameters,
+    resolved: &ResolvedDetails,
+) -> Result<()> {
+    let proj_name = resolved.project_display_name();
+    let proj_id = resolved.project_id();
+    let key_name = subcmd_args.value_of(KEY_ARG);
+    let as_of_arg = subcmd_args.value_of(AS_OF_ARG);
+    let as_of = parse_datetime(as_of_arg);
+    let tag = parse_tag(as_of_arg);
+    let preview = subcmd_args.is_present("preview");
+    let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let env_names = subcmd_args
+        .values_of_lossy("ENV")
+        .unwrap_or_else(|| vec![resolved.environment_display_name().to_string()]);
+    let evaluate = false; // restore the unevaluated values
+    let mask_secrets = false; // needed to write the values back
+
+    let environments = Environments::new();
+    let mut changes: Vec<ValueRestore> = vec![];
+    for env_name in &env_names {
+        let env_id = if env_name == resolved.environment_display_name() {
+            resolved.environment_id().to_string()
+        } else if let Some(id) = environments.get_id(rest_cfg, env_name)? {
+            id
+        } else {
+            error_no_environment_message(env_name);
+            process::exit(65);
+        };
+
+        if let Some(key) = key_name {
+            let Some(current) = parameters.get_details_by_name(
+                rest_cfg,
+                proj_id,
+                &env_id,
+                key,
+                evaluate,
+                false,
+                mask_secrets,
+                None,
+                None,
+            )?
+            else {
+                error_message(format!(
+                    "Did not find parameter '{key}' in project '{proj_name}'.",
+                ));
+                process::exit(66);
+            };
+            if !current.project_url.contains(proj_id) {
+                error_message(format!(
+                    "Parameter '{key}' must be restored from the project where it is defined."
+                ));
+                process::exit(67);
+            }
+            let previous = parameters.get_details_by_name(
+                rest_cfg,
+                proj_id,
+                &env_id,
+                key,
+                evaluate,
+                false,
+                mask_secrets,
+                as_of.clone(),
+                tag.clone(),
+            )?;
+            changes.push(plan_value_restore(
+                env_name,
+                &env_id,
+                &current,
+                previous.as_ref(),
+                show_secrets,
+            ));
+        } else {
+            let current_map = parameters.get_parameter_detail_map(
+                rest_cfg,
+                proj_id,
+                &env_id,
+                true,
+                mask_secrets,
+                None,
+                None,
+            )?;
+            let previous_map = parameters.get_parameter_detail_map(
+                rest_cfg,
+                proj_id,
+                &env_id,
+                true,
+                mask_secrets,
+                as_of.clone(),
+                tag.clone(),
+            )?;
+            let mut keys: Vec<&String> = current_map.keys().collect();
+            keys.sort();
+            for key in keys {
+                changes.push(plan_value_restore(
+                    env_name,
+                    &env_id,
+                    &current_map[key],
+                    previous_map.get(key),
+                    show_secrets,
+                ));
+            }
+        }
+    }
+    changes.retain(|c| c.action != RESTORE_UNCHANGED);
+
+    if changes.is_empty() {
+        println!("Nothing to restore.");
+        return Ok(());
+    }
+    let mut table = Table::new("restore");
+    table.set_header(&["Parameter", "Environment", "Change", "Before", "After"]);
+    for c in &changes {
+        table.add_row(vec![
+            c.key.clone(),
+            c.env_name.clone(),
+            c.action.to_string(),
+            c.before.clone(),
+            c.after.clone(),
+        ]);
+    }
+    table.render(fmt)?;
+    if preview {
+        return Ok(());
+    }
+
+    let mut confirmed = subcmd_args.is_present(CONFIRM_FLAG);
+    if !confirmed {
+        let as_of_str = as_of_arg.unwrap_or_default();
+        confirmed = user_confirm(
+            format!(
+                "Restore {} value(s) in project '{proj_name}' as of '{as_of_str}'",
+                changes.len()
+            ),
+            Some(false),
+        );
+    }
+    if !confirmed {
+        warning_message("No restore done".to_string());
+        return Ok(());
+    }
+
+    for c in &changes {
+        let value = c
+            .previous
+            .as_ref()
+            .filter(|p| !p.external)
+            .map(|p| p.raw_value.as_str());
+        let fqn = c
+            .previous
+            .as_ref()
+            .filter(|p| p.external)
+            .map(|p| p.fqn.as_str());
+        let jmes_path = c
+            .previous
+            .as_ref()
+            .filter(|p| p.external && !p.jmes_path.is_empty())
+            .map(|p| p.jmes_path.as_str());
+        let evaluated = c.previous.as_ref().map(|p| p.evaluated);
+        match c.action {
+            RESTORE_UPDATE => {
+                parameters.update_parameter_value(
+                    rest_cfg,
+                    proj_id,
+                    &c.current.id,
+                    &c.current.val_id,
+                    value,
+                    fqn,
+                    jmes_path,
+                    evaluated,
+                )?;
+            }
+            RESTORE_CREATE => {
+                parameters.create_parameter_value(
+                    rest_cfg,
+                    proj_id,
+                    &c.env_id,
+                    &c.current.id,
+                    value,
+                    fqn,
+                    jmes_path,
+                    evaluated,
+                )?;
+            }
+            _ => {
+                parameters.delete_parameter_value(rest_cfg, proj_id, &c.env_id, &c.key)?;
+            }
+        }
+    }
+    println!(
+        "Restored {} value(s) in project '{proj_name}'.",
+        changes.len()
+    );
+    Ok(())
+}
+
 /// Process the 'parameters' sub-command
 pub fn process_parameters_command(
     subcmd_args: &ArgMatches,
     rest_cfg: &OpenApiConfig,
     resolved: &ResolvedDetails,
+    config: &Config,
 ) -> Result<()> {
     let parameters = Parameters::new();
     if let Some(subcmd_args) = subcmd_args.subcommand_matches(LIST_SUBCMD) {
         proc_param_list(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(GET_SUBCMD) {
-        proc_param_get(subcmd_args, rest_cfg, &parameters, resolved)?;
+        proc_param_get(subcmd_args, rest_cfg, &parameters, resolved, config)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(SET_SUBCMD) {
         proc_param_set(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(DELETE_SUBCMD) {
@@ -1327,11 +1968,15 @@ pub fn process_parameters_command(
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(COPY_SUBCMD) {
         proc_param_copy(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("export") {
-        proc_param_export(subcmd_args, rest_cfg, &parameters, resolved)?;
+        proc_param_export(subcmd_args, rest_cfg, &parameters, resolved, config)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("unset") {
         proc_param_unset(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(DIFF_SUBCMD) {
         proc_param_diff(subcmd_args, rest_cfg, &parameters, resolved)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(HISTORY_SUBCMD) {
+        proc_param_history(subcmd_args, rest_cfg, &parameters, resolved)?;
+    } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("restore") {
+        proc_param_restore(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("environment") {
         proc_param_env(subcmd_args, rest_cfg, &parameters, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(PUSH_SUBCMD) {
@@ -1343,3 +1988,199 @@ pub fn process_parameters_command(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENV_ID: &str = "env-id";
+
+    fn value(raw_value: &str, env_id: &str, secret: bool) -> ParameterDetails {
+        ParameterDetails {
+            key: "param".to_string(),
+            secret,
+            val_id: "val-id".to_string(),
+            env_url: format!("https://localhost/api/v1/environments/{env_id}/"),
+            raw_value: raw_value.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn history(
+        name: &str,
+        action: HistoryAction,
+        model: &str,
+        environments: &[(&str, &str)],
+    ) -> ParameterHistory {
+        ParameterHistory {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+            change_type: action,
+            model: model.to_string(),
+            environments: environments.iter().map(|(n, _)| n.to_string()).collect(),
+            environment_ids: environments.iter().map(|(_, i)| i.to_string()).collect(),
+            inherited: vec![],
+            modified_at: "2024-01-02T03:04:05.000000Z".to_string(),
+            user_name: "user".to_string(),
+        }
+    }
+
+    #[test]
+    fn history_value_rows() {
+        let set_one = history(
+            "param",
+            HistoryAction::Update,
+            "value",
+            &[("default", ENV_ID)],
+        );
+        let set_both = ParameterHistory {
+            modified_at: "2024-01-03T00:00:00.000000Z".to_string(),
+            ..history(
+                "secret",
+                HistoryAction::Create,
+                "value",
+                &[("default", ENV_ID), ("prod", "prod-id")],
+            )
+        };
+        let deleted = history(
+            "param",
+            HistoryAction::Delete,
+            "value",
+            &[("default", ENV_ID)],
+        );
+        let created = history("param", HistoryAction::Create, "parameter", &[]);
+        let entries = vec![
+            set_one.clone(),
+            set_both.clone(),
+            deleted.clone(),
+            created.clone(),
+        ];
+        // one lookup per time, no matter how many parameters or environments changed
+        assert_eq!(
+            history_value_times(&entries)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![set_one.modified_at.clone(), set_both.modified_at.clone()]
+        );
+
+        let mut values = HistoryValues::new();
+        let mut insert = |entry: &ParameterHistory, env_id: &str, value: &str, secret: bool| {
+            let details = ParameterDetails {
+                secret,
+                value: value.to_string(),
+                ..Default::default()
+            };
+            values.insert(
+                (
+                    entry.modified_at.clone(),
+                    entry.name.clone(),
+                    env_id.to_string(),
+                ),
+                details,
+            );
+        };
+        insert(&set_one, ENV_ID, "one", false);
+        insert(&set_both, ENV_ID, "s1", true);
+        insert(&set_both, "prod-id", "s2", true);
+        let secrets = HashMap::from([("secret".to_string(), true)]);
+
+        assert_eq!(history_values(&set_one, &values, &secrets, false), "one");
+        assert_eq!(
+            history_values(&set_both, &values, &secrets, false),
+            format!("default: {REDACTED}\nprod: {REDACTED}")
+        );
+        assert_eq!(
+            history_values(&set_both, &values, &secrets, true),
+            "default: s1\nprod: s2"
+        );
+        assert_eq!(history_values(&deleted, &values, &secrets, true), "");
+        assert_eq!(history_values(&created, &values, &secrets, true), "");
+
+        // a secret without a value at that time is still masked
+        values.clear();
+        assert_eq!(
+            history_values(&set_both, &values, &secrets, false),
+            format!("default: {REDACTED}\nprod: {REDACTED}")
+        );
+        assert_eq!(history_values(&set_one, &values, &secrets, false), "");
+    }
+
+    #[test]
+    fn value_restore_plans() {
+        let inherited = value("parent", "parent-id", false);
+        // (description, current, previous, show secrets, action, before, after)
+        let cases = [
+            (
+                "unchanged",
+                value("one", ENV_ID, false),
+                Some(value("one", ENV_ID, false)),
+                false,
+                RESTORE_UNCHANGED,
+                "one",
+                "one",
+            ),
+            (
+                "changed",
+                value("two", ENV_ID, false),
+                Some(value("one", ENV_ID, false)),
+                false,
+                RESTORE_UPDATE,
+                "two",
+                "one",
+            ),
+            (
+                "deleted since",
+                inherited.clone(),
+                Some(value("one", ENV_ID, false)),
+                false,
+                RESTORE_CREATE,
+                "",
+                "one",
+            ),
+            (
+                "set since",
+                value("two", ENV_ID, false),
+                None,
+                false,
+                RESTORE_UNSET,
+                "two",
+                "",
+            ),
+            (
+                "inherited both times",
+                inherited.clone(),
+                Some(inherited.clone()),
+                false,
+                RESTORE_UNCHANGED,
+                "",
+                "",
+            ),
+            (
+                "secret masked",
+                value("new-secret", ENV_ID, true),
+                Some(value("old-secret", ENV_ID, false)),
+                false,
+                RESTORE_UPDATE,
+                REDACTED,
+                REDACTED,
+            ),
+            (
+                "secret shown",
+                value("new-secret", ENV_ID, true),
+                Some(value("old-secret", ENV_ID, true)),
+                true,
+                RESTORE_UPDATE,
+                "new-secret",
+                "old-secret",
+            ),
+        ];
+        for (description, current, previous, show_secrets, action, before, after) in cases {
+            let plan =
+                plan_value_restore("default", ENV_ID, &current, previous.as_ref(), show_secrets);
+            assert_eq!(plan.action, action, "{description}");
+            assert_eq!(plan.before, before, "{description}");
+            assert_eq!(plan.after, after, "{description}");
+            assert_eq!(plan.previous.is_some(), !after.is_empty(), "{description}");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::{Read, Write};
+
+/// Replacement text for masked values.
+pub const MASK: &str = "*****";
+
+/// Shorter values are not masked, since they would garble unrelated output.
+pub const MIN_MASK_LEN: usize = 4;
+
+/// Streaming replacement of secret values (and their common encodings) with `MASK`.
+///
+/// Output that could be the start of a secret is held back until the next read shows whether it
+/// is, so values split across read boundaries are still masked.
+pub struct Redactor {
+    patterns: Vec<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+/// The forms of a value that commonly show up in logs.
+fn encodings(value: &str) -> Vec<String> {
+    let json = serde_json::to_string(value).unwrap();
+    vec![
+        value.to_string(),
+        base64::encode(value),
+        base64::encode(value).trim_end_matches('=').to_string(),
+        base64::encode_config(value, base64::URL_SAFE_NO_PAD),
+        urlencoding::encode(value).into_owned(),
+        json[1..json.len() - 1].to_string(),
+    ]
+}
+
+impl Redactor {
+    pub fn new(secrets: &[String]) -> Self {
+        let mut patterns: Vec<Vec<u8>> = secrets
+            .iter()
+            .filter(|s| s.len() >= MIN_MASK_LEN)
+            .flat_map(|s| encodings(s))
+            .map(String::into_bytes)
+            .collect();
+        patterns.sort();
+        patterns.dedup();
+        Self {
+            patterns,
+            pending: vec![],
+        }
+    }
+
+    /// Length of the longest pattern at the start of `data`, or `None` when a pattern could still
+    /// match once more data arrives.
+    fn match_len(&self, data: &[u8], more: bool) -> Option<usize> {
+        let mut longest = 0;
+        for pattern in &self.patterns {
+            if data.starts_with(pattern) {
+                longest = longest.max(pattern.len());
+            } else if more && data.len() < pattern.len() && pattern.starts_with(data) {
+                return None;
+            }
+        }
+        Some(longest)
+    }
+
+    fn redact(&mut self, more: bool) -> Vec<u8> {
+        let mut output = vec![];
+        let mut pos = 0;
+        while pos < self.pending.len() {
+            match self.match_len(&self.pending[pos..], more) {
+                None => break,
+                Some(0) => {
+                    output.push(self.pending[pos]);
+                    pos += 1;
+                }
+                Some(len) => {
+                    output.extend_from_slice(MASK.as_bytes());
+                    pos += len;
+                }
+            }
+        }
+        self.pending.drain(..pos);
+        output
+    }
+
+    /// Adds the `data`, and returns the output that is safe to write.
+    pub fn push(&mut self, data: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(data);
+        self.redact(true)
+    }
+
+    /// Returns the remaining output, once there is nothing more to read.
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.redact(false)
+    }
+}
+
+/// Copies the `input` to the `output` until the end of the input, masking the secrets.
+pub fn copy_redacted(
+    mut input: impl Read,
+    mut output: impl Write,
+    mut redactor: Redactor,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    loop {
+        let count = input.read(&mut buffer)?;
+        let data = match count {
+            0 => redactor.finish(),
+            _ => redactor.push(&buffer[..count]),
+        };
+        output.write_all(&data)?;
+        output.flush()?;
+        if count == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact_chunks(secrets: &[&str], chunks: &[&str]) -> String {
+        let secrets: Vec<String> = secrets.iter().map(|s| s.to_string()).collect();
+        let mut redactor = Redactor::new(&secrets);
+        let mut output = vec![];
+        for chunk in chunks {
+            output.extend(redactor.push(chunk.as_bytes()));
+        }
+        output.extend(redactor.finish());
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn redact_across_boundaries() {
+        let secrets = &["hunter22", "hunter2-longer"];
+        assert_eq!(
+            redact_chunks(secrets, &["password=hunter22\n"]),
+            "password=*****\n"
+        );
+        assert_eq!(
+            redact_chunks(secrets, &["pass: hun", "ter", "22 and hunter2-longer"]),
+            "pass: ***** and *****"
+        );
+        // held back output is written once it cannot be a secret
+        assert_eq!(redact_chunks(secrets, &["hunt", "ing"]), "hunting");
+        assert_eq!(
+            redact_chunks(secrets, &["ends with hunte"]),
+            "ends with hunte"
+        );
+        // short values are left alone
+        assert_eq!(redact_chunks(&["abc"], &["abc"]), "abc");
+    }
+
+    #[test]
+    fn redact_encodings() {
+        let secret = "s3cr3t/value?";
+        let output = redact_chunks(
+            &[secret],
+            &[&format!(
+                "{} {} {} {}",
+                base64::encode(secret),
+                base64::encode_config(secret, base64::URL_SAFE_NO_PAD),
+                urlencoding::encode(secret),
+                serde_json::to_string("s3cr3t/value?").unwrap()
+            )],
+        );
+        assert!(!output.contains("czNjcjN0L3ZhbHVlPw"), "{output}");
+        assert!(!output.contains("s3cr3t"), "{output}");
+        assert_eq!(output, "***** ***** ***** \"*****\"");
+    }
+
+    #[test]
+    fn copy_stream() {
+        let input = "line one secret-value\nline two\n".as_bytes();
+        let mut output = vec![];
+        copy_redacted(
+            input,
+            &mut output,
+            Redactor::new(&["secret-value".to_string()]),
+        )
+        .unwrap();
+        assert_eq!(output, b"line one *****\nline two\n");
+    }
+}
@@ -1,27 +1,51 @@
-use crate::cli::AS_OF_ARG;
+use crate::cli::{AS_OF_ARG, FORMAT_OPT, SECRETS_FLAG};
 use crate::database::{OpenApiConfig, Parameters, ResolvedDetails};
-use crate::subprocess::{EnvSettings, Inheritance, SubProcess};
+use crate::key_transform::KeyTransform;
+use crate::lock::{load_lock_file, verify_locked_values};
+use crate::offline_cache::{cached_fetch, cached_resolve_ids, OfflineCache};
+use crate::redactor::{MASK, MIN_MASK_LEN};
+use crate::run_templates::TemplateFiles;
+use crate::subprocess::{
+    changed_keys, merge_layers, signal_from_name, value_fingerprints, EnvSettings, EnvSource,
+    Inheritance, SubProcess, SubProcessError,
+};
+use crate::table::Table;
 use crate::utils::{
-    format_param_error, parse_datetime, parse_tag, warn_missing_subcommand, warn_unresolved_params,
-    warn_user,
+    decrypt_value, encryption_key, error_message, format_param_error, help_message, parse_datetime,
+    parse_duration, parse_tag, warn_missing_subcommand, warn_unresolved_params, warn_user,
+    warning_message,
 };
 use clap::ArgMatches;
+use cloudtruth_config::{Config, CT_API_KEY, CT_TEMPLATE_DIR};
 use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
 use std::process;
 use std::str::FromStr;
+use std::time::Duration;
 
-/// Process the 'run' sub-command
-pub fn process_run_command(
-    subcmd_args: &ArgMatches,
+/// The (decrypted) CloudTruth values for the run environment.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RunValues {
+    pub values: EnvSettings,
+    // values of the secret parameters, for masking the output
+    pub secrets: Vec<String>,
+    // names of the secret parameters (after the key transforms), for the dry-run report
+    #[serde(default)]
+    pub secret_names: Vec<String>,
+    pub errors: Vec<String>,
+    // keys with different values in several layers
+    #[serde(skip)]
+    pub overridden: Vec<String>,
+}
+
+/// Gets the (decrypted) CloudTruth values for the run environment, along with any value errors.
+pub fn get_run_values(
     rest_cfg: &OpenApiConfig,
     resolved: &ResolvedDetails,
-) -> Result<()> {
-    let mut sub_proc = SubProcess::new();
-    let as_of = parse_datetime(subcmd_args.value_of(AS_OF_ARG));
-    let tag = parse_tag(subcmd_args.value_of(AS_OF_ARG));
-    let mut arguments: Vec<String>;
-    let command: String;
-
+    as_of: Option<String>,
+    tag: Option<String>,
+    key_material: Option<&[u8]>,
+) -> Result<RunValues> {
     let parameters = Parameters::new();
     let param_map = parameters.get_parameter_values(
         rest_cfg,
@@ -33,15 +57,251 @@ pub fn process_run_command(
         as_of,
         tag,
     )?;
-    let mut ct_vars = EnvSettings::new();
-    let mut errors: Vec<String> = vec![];
+    let mut result = RunValues::default();
     for (k, v) in param_map {
-        ct_vars.insert(k.clone(), v.value.clone());
+        let value = decrypt_value(&k, &v.value, key_material)?;
+        if v.secret {
+            result.secrets.push(value.clone());
+            result.secret_names.push(k.clone());
+        }
+        result.values.insert(k.clone(), value);
         if !v.error.is_empty() {
-            errors.push(format_param_error(&k, &v.error))
+            result.errors.push(format_param_error(&k, &v.error))
+        }
+    }
+    Ok(result)
+}
+
+/// Resolves the base project/environment, followed by each `--layer proj[:env]` in order. A layer
+/// without an environment uses the base environment.
+fn resolve_layers(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    resolved: &ResolvedDetails,
+    cache: Option<&OfflineCache>,
+) -> Result<Vec<ResolvedDetails>> {
+    let mut layers = vec![resolved.clone()];
+    for layer in subcmd_args.values_of_lossy("layer").unwrap_or_default() {
+        let (proj_name, env_name) = match layer.split_once(':') {
+            Some((proj_name, env_name)) => (proj_name, env_name),
+            None => (layer.as_str(), resolved.environment_display_name()),
+        };
+        if proj_name.is_empty() || env_name.is_empty() {
+            error_message(format!(
+                "Invalid --layer '{layer}', use PROJECT or PROJECT:ENVIRONMENT"
+            ));
+            process::exit(75);
+        }
+        layers.push(cached_resolve_ids(cache, rest_cfg, proj_name, env_name)?);
+    }
+    Ok(layers)
+}
+
+/// Gets the CloudTruth values for every layer, and merges them with later layers taking precedence.
+/// When `strict`, different values for the same key in several layers are an error. The key
+/// transforms are applied to the merged values.
+#[allow(clippy::too_many_arguments)]
+fn get_layered_values(
+    rest_cfg: &OpenApiConfig,
+    layers: &[ResolvedDetails],
+    strict: bool,
+    transform: &KeyTransform,
+    as_of: Option<String>,
+    tag: Option<String>,
+    key_material: Option<&[u8]>,
+    cache: Option<&OfflineCache>,
+) -> Result<RunValues> {
+    let mut layer_values: Vec<EnvSettings> = vec![];
+    let mut result = RunValues::default();
+    for layer in layers {
+        let key = [
+            "run",
+            layer.project_id(),
+            layer.environment_id(),
+            as_of.as_deref().unwrap_or_default(),
+            tag.as_deref().unwrap_or_default(),
+        ];
+        let mut run_values = cached_fetch(cache, &key, || {
+            get_run_values(rest_cfg, layer, as_of.clone(), tag.clone(), key_material)
+        })?;
+        layer_values.push(run_values.values);
+        result.secrets.append(&mut run_values.secrets);
+        result.errors.append(&mut run_values.errors);
+        for name in run_values.secret_names {
+            result.secret_names.push(transform.transform_key(&name));
+        }
+    }
+    let (merged, overridden) = merge_layers(layer_values, strict)?;
+    result.values = transform.apply(merged)?;
+    result.overridden = overridden;
+    Ok(result)
+}
+
+/// Warns about the parameters where a later layer overrides a different value.
+fn warn_overridden(overridden: &[String]) {
+    if !overridden.is_empty() {
+        warning_message(format!(
+            "Layers have different values for: {} (later layers take precedence, use --layer-strict to refuse)",
+            overridden.join(", ")
+        ));
+    }
+}
+
+/// Masks the secret values in the command output, and warns about values too short to mask.
+fn set_masked_values(sub_proc: &mut SubProcess, secrets: Vec<String>) {
+    if secrets
+        .iter()
+        .any(|s| !s.is_empty() && s.len() < MIN_MASK_LEN)
+    {
+        warning_message(format!(
+            "Secret values shorter than {MIN_MASK_LEN} characters are not masked in the output"
+        ));
+    }
+    sub_proc.set_masked_values(secrets);
+}
+
+fn duration_arg(subcmd_args: &ArgMatches, name: &str, default_value: &str) -> Duration {
+    let value = subcmd_args.value_of(name).unwrap_or(default_value);
+    match parse_duration(value) {
+        Some(duration) => duration,
+        None => {
+            error_message(format!(
+                "Invalid --{name} value '{value}', use a non-zero duration like 500ms, 30s, 5m or 1h"
+            ));
+            process::exit(72);
         }
     }
-    sub_proc.set_cloudtruth_environment(ct_vars);
+}
+
+/// Sets up the environment for the sub-process from the CloudTruth values and the CLI arguments.
+fn setup_environment(
+    sub_proc: &mut SubProcess,
+    subcmd_args: &ArgMatches,
+    resolved: &ResolvedDetails,
+    template_dir: Option<&str>,
+) -> Result<()> {
+    let inherit = Inheritance::from_str(subcmd_args.value_of("inheritance").unwrap()).unwrap();
+    let overrides = subcmd_args.values_of_lossy("set").unwrap_or_default();
+    let removals = subcmd_args.values_of_lossy("remove").unwrap_or_default();
+    let permissive = subcmd_args.is_present("permissive");
+    let strict = subcmd_args.is_present("strict");
+    let result = sub_proc.set_environment(resolved, inherit, &overrides, &removals, strict);
+    if !permissive {
+        sub_proc.remove_ct_app_vars();
+    }
+    if let Some(dir) = template_dir {
+        sub_proc.set_variable(CT_TEMPLATE_DIR, dir);
+    }
+    Ok(result?)
+}
+
+/// Shows how the run environment was computed, instead of running the command.
+fn proc_run_dry_run(
+    subcmd_args: &ArgMatches,
+    sub_proc: &mut SubProcess,
+    resolved: &ResolvedDetails,
+    secret_names: &[String],
+) -> Result<()> {
+    let show_secrets = subcmd_args.is_present(SECRETS_FLAG);
+    let fmt = subcmd_args.value_of(FORMAT_OPT).unwrap();
+    let result = setup_environment(sub_proc, subcmd_args, resolved, None);
+    if let Err(e) = &result {
+        // collisions are shown in the table, other problems mean there is nothing to show
+        if !matches!(
+            e.downcast_ref::<SubProcessError>(),
+            Some(SubProcessError::EnvironmentCollisions(_))
+        ) {
+            return result;
+        }
+    }
+
+    let mut table = Table::new("variable");
+    table.set_header(&["Name", "Value", "Source", "Overrides", "Status"]);
+    for (name, entry) in sub_proc.environment_report() {
+        // only the values that came from a secret parameter are masked
+        let secret = name == CT_API_KEY
+            || (entry.source == EnvSource::CloudTruth && secret_names.contains(name));
+        let value = if !show_secrets && secret {
+            MASK.to_string()
+        } else {
+            entry.value.clone()
+        };
+        let overrides: Vec<String> = entry.overrides.iter().map(|s| s.to_string()).collect();
+        let status = if entry.collision {
+            "collision"
+        } else if entry.removed {
+            "removed"
+        } else {
+            ""
+        };
+        table.add_row(vec![
+            name.clone(),
+            value,
+            entry.source.to_string(),
+            overrides.join(", "),
+            status.to_string(),
+        ]);
+    }
+    table.render(fmt)?;
+    result
+}
+
+/// Process the 'run' sub-command
+pub fn process_run_command(
+    subcmd_args: &ArgMatches,
+    rest_cfg: &OpenApiConfig,
+    resolved: &ResolvedDetails,
+    config: &Config,
+) -> Result<()> {
+    let mut sub_proc = SubProcess::new();
+    let locked = subcmd_args
+        .value_of("locked")
+        .map(|path| (path, load_lock_file(path, resolved)));
+    let (as_of, tag) = match &locked {
+        Some((_, lock)) => (lock.as_of.clone(), lock.tag.clone()),
+        None => (
+            parse_datetime(subcmd_args.value_of(AS_OF_ARG)),
+            parse_tag(subcmd_args.value_of(AS_OF_ARG)),
+        ),
+    };
+    let mut arguments: Vec<String>;
+    let command: String;
+
+    let key_material = encryption_key(config.encryption_key_file.as_deref());
+    let cache = OfflineCache::new(config);
+    let layers = resolve_layers(subcmd_args, rest_cfg, resolved, cache.as_ref())?;
+    let layer_strict = subcmd_args.is_present("layer-strict");
+    let transform = KeyTransform::new(subcmd_args, config);
+    let run_values = get_layered_values(
+        rest_cfg,
+        &layers,
+        layer_strict,
+        &transform,
+        as_of.clone(),
+        tag.clone(),
+        key_material.as_deref(),
+        cache.as_ref(),
+    )?;
+    if let Some((path, lock)) = &locked {
+        verify_locked_values(lock, path, &run_values.values, &transform);
+    }
+    let mut fingerprints = value_fingerprints(&run_values.values);
+    if subcmd_args.is_present("dry-run") {
+        warn_unresolved_params(&run_values.errors);
+        warn_overridden(&run_values.overridden);
+        sub_proc.set_cloudtruth_environment(run_values.values);
+        return proc_run_dry_run(
+            subcmd_args,
+            &mut sub_proc,
+            resolved,
+            &run_values.secret_names,
+        );
+    }
+    let mask_output = subcmd_args.is_present("mask-output");
+    if mask_output {
+        set_masked_values(&mut sub_proc, run_values.secrets);
+    }
+    sub_proc.set_cloudtruth_environment(run_values.values);
 
     if subcmd_args.is_present("command") {
         command = subcmd_args.value_of("command").unwrap().to_string();
@@ -64,19 +324,99 @@ pub fn process_run_command(
     }
 
     // NOTE: do this before running the sub-process, since it could be a long-running task
-    warn_unresolved_params(&errors);
+    warn_unresolved_params(&run_values.errors);
+    warn_overridden(&run_values.overridden);
 
-    // Setup the environment for the sub-process.
-    let inherit = Inheritance::from_str(subcmd_args.value_of("inheritance").unwrap()).unwrap();
-    let overrides = subcmd_args.values_of_lossy("set").unwrap_or_default();
-    let removals = subcmd_args.values_of_lossy("remove").unwrap_or_default();
-    let permissive = subcmd_args.is_present("permissive");
-    let strict = subcmd_args.is_present("strict");
-    sub_proc.set_environment(resolved, inherit, &overrides, &removals, strict)?;
-    if !permissive {
-        sub_proc.remove_ct_app_vars();
+    // Render any templates before the environment, so the directory can be passed along.
+    let template_args = subcmd_args.values_of_lossy("template").unwrap_or_default();
+    let mut template_files =
+        TemplateFiles::new(&template_args, subcmd_args.is_present("template-dir"))?;
+    let template_dir = template_files.dir();
+    template_files.render(
+        rest_cfg,
+        resolved,
+        as_of.clone(),
+        tag.clone(),
+        key_material.as_deref(),
+    )?;
+
+    setup_environment(
+        &mut sub_proc,
+        subcmd_args,
+        resolved,
+        template_dir.as_deref(),
+    )?;
+    if subcmd_args.is_present("exec") {
+        #[cfg(unix)]
+        return Err(sub_proc.exec_command(command.as_str(), &arguments).into());
+        #[cfg(not(unix))]
+        warn_user(
+            "The --exec option is only supported on Unix, running as a sub-process.".to_string(),
+        );
+    }
+    if !subcmd_args.is_present("watch") {
+        let exit_code = sub_proc.run_command(command.as_str(), &arguments)?;
+        template_files.cleanup();
+        process::exit(exit_code)
     }
-    sub_proc.run_command(command.as_str(), &arguments)?;
 
-    Ok(())
+    let interval = duration_arg(subcmd_args, "interval", "30s");
+    let grace = duration_arg(subcmd_args, "grace-period", "10s");
+    let signal =
+        signal_from_name(subcmd_args.value_of("restart-signal").unwrap_or("TERM")).unwrap();
+    let mut child = sub_proc.spawn_command(command.as_str(), &arguments)?;
+    loop {
+        if let Some(exit_code) = child.wait_timeout(interval)? {
+            template_files.cleanup();
+            process::exit(exit_code)
+        }
+        let run_values = match get_layered_values(
+            rest_cfg,
+            &layers,
+            layer_strict,
+            &transform,
+            as_of.clone(),
+            tag.clone(),
+            key_material.as_deref(),
+            cache.as_ref(),
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                warning_message(format!("Failed to check for parameter changes: {e}"));
+                continue;
+            }
+        };
+        let updated = value_fingerprints(&run_values.values);
+        let changed = changed_keys(&fingerprints, &updated);
+        if changed.is_empty() {
+            continue;
+        }
+
+        help_message(format!(
+            "Parameters changed ({}), restarting the command",
+            changed.join(", ")
+        ));
+        warn_unresolved_params(&run_values.errors);
+        warn_overridden(&run_values.overridden);
+        child.stop(signal, grace)?;
+        template_files.render(
+            rest_cfg,
+            resolved,
+            as_of.clone(),
+            tag.clone(),
+            key_material.as_deref(),
+        )?;
+        if mask_output {
+            set_masked_values(&mut sub_proc, run_values.secrets);
+        }
+        sub_proc.set_cloudtruth_environment(run_values.values);
+        setup_environment(
+            &mut sub_proc,
+            subcmd_args,
+            resolved,
+            template_dir.as_deref(),
+        )?;
+        fingerprints = updated;
+        child = sub_proc.spawn_command(command.as_str(), &arguments)?;
+    }
 }
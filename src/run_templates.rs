@@ -0,0 +1,285 @@
+use crate::database::{OpenApiConfig, ResolvedDetails, Templates};
+use crate::utils::decrypt_value;
+use color_eyre::eyre::Result;
+use std::error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Problems with the templates. These are returned (rather than exiting), so `TemplateFiles` is
+/// dropped and removes whatever was already written.
+#[derive(Debug)]
+pub enum TemplateError {
+    InvalidArg(String),
+    AlreadyExists(String, PathBuf),
+    Write(String, PathBuf, String),
+}
+
+impl error::Error for TemplateError {}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            TemplateError::InvalidArg(arg) => write!(
+                f,
+                "Invalid --template '{arg}', use NAME=PATH (or NAME with --template-dir)"
+            ),
+            TemplateError::AlreadyExists(name, path) => write!(
+                f,
+                "Template '{name}' path '{}' already exists, and will not be overwritten",
+                path.display()
+            ),
+            TemplateError::Write(name, path, e) => write!(
+                f,
+                "Failed to write template '{name}' to '{}': {e}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// The template files written for a `run` command, which are removed when the run completes.
+pub struct TemplateFiles {
+    templates: Vec<(String, PathBuf)>,
+    dir: Option<TempDir>,
+    written: Vec<PathBuf>,
+}
+
+/// Creates a private directory, preferring memory-backed storage where it is available.
+fn private_dir() -> Result<TempDir> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("cloudtruth-");
+    let shm = Path::new("/dev/shm");
+    let dir = if cfg!(target_os = "linux") && shm.is_dir() {
+        builder.tempdir_in(shm).or_else(|_| builder.tempdir())?
+    } else {
+        builder.tempdir()?
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(dir)
+}
+
+/// Writes the file so only the current user can read it. Unless `overwrite` is set, the file
+/// must not exist yet.
+pub fn write_private(path: &Path, body: &str, overwrite: bool) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    if overwrite {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.write(true).create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // a re-written file keeps its mode, so tighten it
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(body.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        let mut file = options.open(path)?;
+        file.write_all(body.as_bytes())
+    }
+}
+
+/// Writes a template file. Only files written by this run are overwritten (e.g. when the
+/// templates are re-rendered), so an existing file is never replaced or removed by the cleanup.
+fn write_template(written: &mut Vec<PathBuf>, path: &Path, body: &str) -> std::io::Result<()> {
+    let overwrite = written.iter().any(|p| p == path);
+    write_private(path, body, overwrite)?;
+    if !overwrite {
+        written.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Splits a `NAME=PATH` argument. Without a path, the template name is used as the file name
+/// in the template directory.
+fn parse_template_arg(arg: &str, dir: Option<&Path>) -> Option<(String, PathBuf)> {
+    let (name, path) = match arg.split_once('=') {
+        Some((name, path)) => (name, PathBuf::from(path)),
+        None => (arg, PathBuf::from(arg)),
+    };
+    if name.is_empty() || path.as_os_str().is_empty() {
+        return None;
+    }
+    match dir {
+        Some(dir) if path.is_relative() => Some((name.to_string(), dir.join(path))),
+        None if !arg.contains('=') => None,
+        _ => Some((name.to_string(), path)),
+    }
+}
+
+impl TemplateFiles {
+    pub fn new(template_args: &[String], use_dir: bool) -> Result<Self> {
+        let dir = if use_dir { Some(private_dir()?) } else { None };
+        let mut templates = vec![];
+        for arg in template_args {
+            match parse_template_arg(arg, dir.as_ref().map(|d| d.path())) {
+                Some(template) => templates.push(template),
+                None => return Err(TemplateError::InvalidArg(arg.clone()).into()),
+            }
+        }
+        Ok(Self {
+            templates,
+            dir,
+            written: vec![],
+        })
+    }
+
+    /// The private template directory, when one was requested.
+    pub fn dir(&self) -> Option<String> {
+        self.dir
+            .as_ref()
+            .map(|d| d.path().to_string_lossy().to_string())
+    }
+
+    /// Evaluates each template for the resolved environment, and writes it to its path.
+    pub fn render(
+        &mut self,
+        rest_cfg: &OpenApiConfig,
+        resolved: &ResolvedDetails,
+        as_of: Option<String>,
+        tag: Option<String>,
+        key_material: Option<&[u8]>,
+    ) -> Result<()> {
+        let templates = Templates::new();
+        let env_name = resolved.environment_display_name().to_string();
+        let mut bodies = vec![];
+        for (name, _) in &self.templates {
+            let details = templates.get_details_by_name(
+                rest_cfg,
+                resolved.project_display_name(),
+                resolved.project_id(),
+                name,
+                true,
+                true,
+                Some(env_name.clone()),
+                as_of.clone(),
+                tag.clone(),
+            )?;
+            bodies.push(decrypt_value(name, &details.body, key_material)?);
+        }
+        self.write_all(&bodies)?;
+        Ok(())
+    }
+
+    /// Writes the evaluated `bodies` (in the same order as the templates) to their paths.
+    fn write_all(&mut self, bodies: &[String]) -> Result<(), TemplateError> {
+        for ((name, path), body) in self.templates.iter().zip(bodies) {
+            write_template(&mut self.written, path, body).map_err(|e| match e.kind() {
+                ErrorKind::AlreadyExists => {
+                    TemplateError::AlreadyExists(name.clone(), path.clone())
+                }
+                _ => TemplateError::Write(name.clone(), path.clone(), e.to_string()),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Removes the written files and the template directory.
+    pub fn cleanup(&mut self) {
+        for path in self.written.drain(..) {
+            let _ = fs::remove_file(path);
+        }
+        if let Some(dir) = self.dir.take() {
+            let _ = dir.close();
+        }
+    }
+}
+
+impl Drop for TemplateFiles {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_args() {
+        let dir = Path::new("/tmp/ct");
+        assert_eq!(
+            parse_template_arg("cfg=/etc/app.yaml", None),
+            Some(("cfg".to_string(), PathBuf::from("/etc/app.yaml")))
+        );
+        assert_eq!(
+            parse_template_arg("cfg=app.yaml", Some(dir)),
+            Some(("cfg".to_string(), PathBuf::from("/tmp/ct/app.yaml")))
+        );
+        assert_eq!(
+            parse_template_arg("cfg", Some(dir)),
+            Some(("cfg".to_string(), PathBuf::from("/tmp/ct/cfg")))
+        );
+        assert_eq!(parse_template_arg("cfg", None), None);
+        assert_eq!(parse_template_arg("=app.yaml", None), None);
+        assert_eq!(parse_template_arg("cfg=", None), None);
+    }
+
+    #[test]
+    fn private_files_are_removed() {
+        let mut files = TemplateFiles::new(&["cfg".to_string()], true).unwrap();
+        let dir = PathBuf::from(files.dir().unwrap());
+        let path = files.templates[0].1.clone();
+        write_template(&mut files.written, &path, "secret: value\n").unwrap();
+        // re-rendering overwrites the file written by this run
+        write_template(&mut files.written, &path, "secret: changed\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret: changed\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        files.cleanup();
+        assert!(!path.exists());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn written_files_are_removed_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.yaml");
+        let second = dir.path().join("second.yaml");
+        fs::write(&second, "checked: in\n").unwrap();
+        let args = [
+            format!("one={}", first.display()),
+            format!("two={}", second.display()),
+        ];
+        let mut files = TemplateFiles::new(&args, false).unwrap();
+        let bodies = ["secret: one\n".to_string(), "secret: two\n".to_string()];
+        let err = files.write_all(&bodies).unwrap_err();
+        assert!(matches!(err, TemplateError::AlreadyExists(name, _) if name == "two"));
+        assert!(first.exists());
+        // returning the error drops the files, which removes the first template
+        drop(files);
+        assert!(!first.exists());
+        assert_eq!(fs::read_to_string(&second).unwrap(), "checked: in\n");
+    }
+
+    #[test]
+    fn existing_files_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.yaml");
+        fs::write(&path, "checked: in\n").unwrap();
+        let arg = format!("cfg={}", path.display());
+        let mut files = TemplateFiles::new(&[arg], false).unwrap();
+        let err = write_template(&mut files.written, &path, "secret: value\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        files.cleanup();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "checked: in\n");
+    }
+}
@@ -0,0 +1,545 @@
+use crate::database::OpenApiConfig;
+use crate::key_transform::KeyTransform;
+use crate::offline_cache::{cached_fetch, cached_resolve_ids, OfflineCache};
+use crate::run::get_run_values;
+use crate::subprocess::EnvSettings;
+use crate::utils::{encryption_key, error_message, parse_duration, warning_message};
+use clap::ArgMatches;
+use cloudtruth_config::{
+    binary_name, Config, CT_APP_REMOVABLE_VARS, CT_ENVIRONMENT, CT_PROFILE, CT_PROJECT,
+    DEFAULT_ENV_NAME,
+};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Project-local settings file, looked for in the current directory and its parents.
+pub const PROJECT_FILE: &str = ".cloudtruth.yaml";
+
+/// Variable the hook uses to remember what it loaded between prompts.
+const HOOK_STATE_VAR: &str = "CLOUDTRUTH_HOOK_STATE";
+
+/// The project files approved with 'shell-hook allow', by canonical path, with the SHA-256 digest
+/// of the approved contents. A project file can set any project, profile and environment, so it
+/// is not loaded until the user approves it.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct AllowedFiles {
+    files: BTreeMap<String, String>,
+}
+
+impl AllowedFiles {
+    fn load(store: &Path) -> Self {
+        fs::read(store)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, store: &Path) -> Result<()> {
+        if let Some(dir) = store.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(store, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn entry(file: &Path, contents: &[u8]) -> Result<(String, String)> {
+        let path = fs::canonicalize(file)?.display().to_string();
+        Ok((path, hex::encode(Sha256::digest(contents))))
+    }
+
+    fn allow(&mut self, file: &Path, contents: &[u8]) -> Result<()> {
+        let (path, digest) = Self::entry(file, contents)?;
+        self.files.insert(path, digest);
+        Ok(())
+    }
+
+    fn is_allowed(&self, file: &Path, contents: &[u8]) -> bool {
+        match Self::entry(file, contents) {
+            Ok((path, digest)) => self.files.get(&path) == Some(&digest),
+            Err(_) => false,
+        }
+    }
+}
+
+fn allowed_store() -> Result<PathBuf> {
+    Config::shell_hook_allowed_file()
+        .ok_or_else(|| eyre!("Cannot find the configuration directory"))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProjectFile {
+    profile: Option<String>,
+    project: Option<String>,
+    environment: Option<String>,
+}
+
+/// What the hook exported, so it can be undone when leaving the directory.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct HookState {
+    file: String,
+    // seconds since the epoch
+    loaded: u64,
+    vars: Vec<String>,
+    // values the exported variables had before, restored instead of unsetting them
+    previous: BTreeMap<String, String>,
+}
+
+impl HookState {
+    fn from_env() -> Self {
+        env::var(HOOK_STATE_VAR)
+            .ok()
+            .and_then(|s| base64::decode(s).ok())
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn encode(&self) -> String {
+        base64::encode(serde_json::to_vec(self).unwrap())
+    }
+
+    /// The value the variable had before the hook exported anything.
+    fn original_value(&self, name: &str) -> Option<String> {
+        if self.vars.iter().any(|v| v == name) {
+            self.previous.get(name).cloned()
+        } else {
+            env::var(name).ok()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl HookShell {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            _ => Self::Bash,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+        }
+    }
+
+    fn quote(&self, value: &str) -> String {
+        match self {
+            Self::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            _ => format!("'{}'", value.replace('\'', "'\\''")),
+        }
+    }
+
+    fn export(&self, name: &str, value: &str) -> String {
+        match self {
+            Self::Fish => format!("set -gx {} {};\n", name, self.quote(value)),
+            _ => format!("export {}={};\n", name, self.quote(value)),
+        }
+    }
+
+    fn unset(&self, name: &str) -> String {
+        match self {
+            Self::Fish => format!("set -e {name};\n"),
+            _ => format!("unset {name};\n"),
+        }
+    }
+
+    /// The script the user adds to their shell startup, which runs the update on every prompt.
+    fn hook_script(&self, binary: &str, ttl: &str) -> String {
+        let update = format!(
+            "{} shell-hook {} --update --ttl {}",
+            self.quote(binary),
+            self.name(),
+            self.quote(ttl)
+        );
+        match self {
+            Self::Bash => format!(
+                r#"_cloudtruth_hook() {{
+  local previous_exit_status=$?
+  eval "$({update})"
+  return $previous_exit_status
+}}
+if [[ ";${{PROMPT_COMMAND[*]:-}};" != *";_cloudtruth_hook;"* ]]; then
+  PROMPT_COMMAND="_cloudtruth_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+"#
+            ),
+            Self::Zsh => format!(
+                r#"_cloudtruth_hook() {{
+  eval "$({update})"
+}}
+typeset -ag precmd_functions
+if (( ! ${{precmd_functions[(I)_cloudtruth_hook]}} )); then
+  precmd_functions=(_cloudtruth_hook $precmd_functions)
+fi
+typeset -ag chpwd_functions
+if (( ! ${{chpwd_functions[(I)_cloudtruth_hook]}} )); then
+  chpwd_functions=(_cloudtruth_hook $chpwd_functions)
+fi
+"#
+            ),
+            Self::Fish => format!(
+                r#"function __cloudtruth_hook --on-event fish_prompt
+    {update} | source
+end
+"#
+            ),
+        }
+    }
+}
+
+/// Finds the closest project file, starting with the `dir`.
+fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_FILE))
+        .find(|f| f.is_file())
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Gets the variables for the project file: the CloudTruth values, plus the profile, project and
+/// environment it names, so other CLI commands in the directory use them too.
+fn load_values(
+    path: &Path,
+    api_key: Option<&str>,
+    profile_name: Option<&str>,
+) -> Result<EnvSettings> {
+    // the approved contents are the ones parsed, so the file cannot change in between
+    let contents = fs::read(path)?;
+    if !AllowedFiles::load(&allowed_store()?).is_allowed(path, &contents) {
+        return Err(eyre!(
+            "{} is not allowed, or has changed since it was allowed. Review it, and run '{} shell-hook allow' to load it",
+            path.display(),
+            binary_name()
+        ));
+    }
+    let project_file: ProjectFile = serde_yaml::from_slice(&contents)?;
+    let profile_name = project_file.profile.as_deref().or(profile_name);
+    let config = Config::load_config(
+        api_key,
+        profile_name,
+        project_file.environment.as_deref(),
+        project_file.project.as_deref(),
+    )?;
+    let rest_cfg = OpenApiConfig::from(&config);
+    let proj_name = config
+        .project
+        .as_deref()
+        .ok_or_else(|| eyre!("No project is set in {}", path.display()))?;
+    let env_name = config.environment.as_deref().unwrap_or(DEFAULT_ENV_NAME);
+    let cache = OfflineCache::new(&config);
+    let resolved = cached_resolve_ids(cache.as_ref(), &rest_cfg, proj_name, env_name)?;
+    let key_material = encryption_key(config.encryption_key_file.as_deref());
+    let key = [
+        "run",
+        resolved.project_id(),
+        resolved.environment_id(),
+        "",
+        "",
+    ];
+    let run_values = cached_fetch(cache.as_ref(), &key, || {
+        get_run_values(&rest_cfg, &resolved, None, None, key_material.as_deref())
+    })?;
+
+    let transform = KeyTransform::new(&ArgMatches::default(), &config);
+    let mut values = transform.apply(run_values.values)?;
+    values.retain(|k, _| !CT_APP_REMOVABLE_VARS.contains(&k.as_str()));
+    let invalid: Vec<String> = values
+        .keys()
+        .filter(|k| !is_valid_name(k))
+        .cloned()
+        .collect();
+    if !invalid.is_empty() {
+        warning_message(format!(
+            "Skipping parameters that are not valid variable names: {}",
+            invalid.join(", ")
+        ));
+        values.retain(|k, _| is_valid_name(k));
+    }
+    if let Some(profile) = project_file.profile {
+        values.insert(CT_PROFILE.to_string(), profile);
+    }
+    values.insert(CT_PROJECT.to_string(), proj_name.to_string());
+    values.insert(CT_ENVIRONMENT.to_string(), env_name.to_string());
+    Ok(values)
+}
+
+/// Shell commands that undo the previous exports, export the new `values` (if any), and save the
+/// new state.
+fn update_commands(
+    shell: HookShell,
+    state: &HookState,
+    file: Option<&Path>,
+    values: Option<&EnvSettings>,
+) -> String {
+    let mut result = String::new();
+    for name in &state.vars {
+        match state.previous.get(name) {
+            Some(value) => result.push_str(&shell.export(name, value)),
+            None => result.push_str(&shell.unset(name)),
+        }
+    }
+    let file = match file {
+        Some(file) => file,
+        None => {
+            result.push_str(&shell.unset(HOOK_STATE_VAR));
+            return result;
+        }
+    };
+    let mut new_state = HookState {
+        file: file.display().to_string(),
+        loaded: now_secs(),
+        ..Default::default()
+    };
+    for (name, value) in values.into_iter().flatten() {
+        if let Some(previous) = state.original_value(name) {
+            new_state.previous.insert(name.clone(), previous);
+        }
+        new_state.vars.push(name.clone());
+        result.push_str(&shell.export(name, value));
+    }
+    result.push_str(&shell.export(HOOK_STATE_VAR, &new_state.encode()));
+    result
+}
+
+/// Prints the commands to run at the prompt, which are empty unless the directory has changed
+/// projects or the values are older than the `ttl`.
+fn proc_hook_update(
+    shell: HookShell,
+    ttl: Duration,
+    api_key: Option<&str>,
+    profile_name: Option<&str>,
+) -> Result<()> {
+    let state = HookState::from_env();
+    let file = find_project_file(&env::current_dir()?);
+    let file_name = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_default();
+    if file_name == state.file {
+        let expired = state.loaded + ttl.as_secs() <= now_secs();
+        // allowing the file is like editing it, so it gets loaded at the next prompt
+        let edited = file.as_deref().map(modified_secs).unwrap_or_default() > state.loaded
+            || allowed_store()
+                .map(|s| modified_secs(&s))
+                .unwrap_or_default()
+                > state.loaded;
+        if !expired && !edited {
+            return Ok(());
+        }
+    }
+
+    let values = match &file {
+        None => None,
+        Some(path) => {
+            // load without the variables from the previous project file
+            for name in &state.vars {
+                match state.previous.get(name) {
+                    Some(value) => env::set_var(name, value),
+                    None => env::remove_var(name),
+                }
+            }
+            match load_values(path, api_key, profile_name) {
+                Ok(values) => Some(values),
+                Err(e) => {
+                    warning_message(format!(
+                        "Failed to load CloudTruth values for {}: {}",
+                        path.display(),
+                        e
+                    ));
+                    if file_name == state.file {
+                        // keep the current values, and try again after the TTL
+                        let retry = HookState {
+                            loaded: now_secs(),
+                            ..state
+                        };
+                        print!("{}", shell.export(HOOK_STATE_VAR, &retry.encode()));
+                        return Ok(());
+                    }
+                    None
+                }
+            }
+        }
+    };
+    print!(
+        "{}",
+        update_commands(shell, &state, file.as_deref(), values.as_ref())
+    );
+    Ok(())
+}
+
+/// Approves the current contents of the project file, so the hook loads it.
+fn proc_hook_allow(subcmd_args: &ArgMatches) -> Result<()> {
+    let file = match subcmd_args.value_of("PATH") {
+        Some(path) => Some(PathBuf::from(path)).filter(|p| p.is_file()),
+        None => find_project_file(&env::current_dir()?),
+    };
+    let file = match file {
+        Some(file) => file,
+        None => {
+            error_message(format!("No {PROJECT_FILE} file found"));
+            process::exit(82);
+        }
+    };
+    let store = allowed_store()?;
+    let mut allowed = AllowedFiles::load(&store);
+    allowed.allow(&file, &fs::read(&file)?)?;
+    allowed.save(&store)?;
+    println!("Allowed the shell hook to load {}", file.display());
+    Ok(())
+}
+
+/// Process the 'shell-hook' sub-command
+pub fn process_shell_hook_command(
+    subcmd_args: &ArgMatches,
+    api_key: Option<&str>,
+    profile_name: Option<&str>,
+) -> Result<()> {
+    if let Some(allow_args) = subcmd_args.subcommand_matches("allow") {
+        return proc_hook_allow(allow_args);
+    }
+    let shell = HookShell::from_name(subcmd_args.value_of("SHELL").unwrap());
+    let ttl_arg = subcmd_args.value_of("ttl").unwrap_or("5m");
+    let ttl = match parse_duration(ttl_arg) {
+        Some(ttl) => ttl,
+        None => {
+            error_message(format!(
+                "Invalid --ttl value '{ttl_arg}', use a non-zero duration like 500ms, 30s, 5m or 1h"
+            ));
+            process::exit(78);
+        }
+    };
+    if subcmd_args.is_present("update") {
+        return proc_hook_update(shell, ttl, api_key, profile_name);
+    }
+    let binary = env::current_exe()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| binary_name());
+    print!("{}", shell.hook_script(&binary, ttl_arg));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_quoting() {
+        let bash = HookShell::Bash;
+        assert_eq!(bash.export("A", "it's"), "export A='it'\\''s';\n");
+        assert_eq!(bash.unset("A"), "unset A;\n");
+        let fish = HookShell::Fish;
+        assert_eq!(fish.export("A", "it's \\"), "set -gx A 'it\\'s \\\\';\n");
+        assert!(is_valid_name("_DB_HOST1"));
+        assert!(!is_valid_name("db.host"));
+        assert!(!is_valid_name("1ABC"));
+    }
+
+    #[test]
+    fn hook_update_restores_previous() {
+        let shell = HookShell::Bash;
+        let state = HookState {
+            file: "/a/.cloudtruth.yaml".to_string(),
+            loaded: 1,
+            vars: vec!["HOME".to_string(), "ONE".to_string()],
+            previous: BTreeMap::from([("HOME".to_string(), "/home/me".to_string())]),
+        };
+        assert_eq!(
+            update_commands(shell, &state, None, None),
+            format!("export HOME='/home/me';\nunset ONE;\nunset {HOOK_STATE_VAR};\n")
+        );
+
+        let values = EnvSettings::from([("HOME".to_string(), "/other".to_string())]);
+        let commands = update_commands(
+            shell,
+            &state,
+            Some(Path::new("/b/.cloudtruth.yaml")),
+            Some(&values),
+        );
+        assert!(commands.contains("export HOME='/other';\n"));
+        let encoded = commands
+            .lines()
+            .last()
+            .unwrap()
+            .trim_start_matches(&format!("export {HOOK_STATE_VAR}='"))
+            .trim_end_matches("';");
+        let new_state: HookState =
+            serde_json::from_slice(&base64::decode(encoded).unwrap()).unwrap();
+        assert_eq!(new_state.file, "/b/.cloudtruth.yaml");
+        assert_eq!(new_state.vars, vec!["HOME".to_string()]);
+        // the original value is kept, not the one exported for the previous project
+        assert_eq!(new_state.previous.get("HOME").unwrap(), "/home/me");
+    }
+
+    #[test]
+    fn hook_allowed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("config").join("allowed.json");
+        let file = dir.path().join(PROJECT_FILE);
+        let other = dir.path().join("other.yaml");
+        fs::write(&file, "project: proj\n").unwrap();
+        fs::write(&other, "project: proj\n").unwrap();
+
+        let mut allowed = AllowedFiles::load(&store);
+        assert!(!allowed.is_allowed(&file, b"project: proj\n"));
+        allowed.allow(&file, b"project: proj\n").unwrap();
+        allowed.save(&store).unwrap();
+
+        let loaded = AllowedFiles::load(&store);
+        assert_eq!(loaded, allowed);
+        assert!(loaded.is_allowed(&file, b"project: proj\n"));
+        // the same file through another path
+        assert!(loaded.is_allowed(&dir.path().join(".").join(PROJECT_FILE), b"project: proj\n"));
+        // modified contents, or another file with the same contents
+        assert!(!loaded.is_allowed(&file, b"project: other\n"));
+        assert!(!loaded.is_allowed(&other, b"project: proj\n"));
+    }
+
+    #[test]
+    fn hook_finds_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        assert!(find_project_file(&nested).is_none());
+        fs::write(dir.path().join(PROJECT_FILE), "project: proj\n").unwrap();
+        assert_eq!(
+            find_project_file(&nested).unwrap(),
+            dir.path().join(PROJECT_FILE)
+        );
+    }
+}
@@ -1,13 +1,17 @@
 use crate::database::ResolvedDetails;
+use crate::redactor::{copy_redacted, Redactor};
 use crate::utils::{default, warn_user};
 use cloudtruth_config::{CT_APP_REMOVABLE_VARS, CT_ENVIRONMENT, CT_PROJECT};
 use color_eyre::eyre::{ErrReport, Result};
 use color_eyre::Report;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
-use std::{env, error};
-use subprocess::{Exec, PopenError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{env, error, io};
+use subprocess::{Exec, ExitStatus, Popen, PopenError, Redirection};
 
 // for improved readability
 pub type EnvSettings = HashMap<String, String>;
@@ -41,12 +45,66 @@ impl FromStr for Inheritance {
     }
 }
 
+/// Where a run environment variable gets its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvSource {
+    Shell,
+    CloudTruth,
+    Override,
+    Breadcrumb,
+}
+
+impl Display for EnvSource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EnvSource::Shell => write!(f, "shell"),
+            EnvSource::CloudTruth => write!(f, "CloudTruth"),
+            EnvSource::Override => write!(f, "override"),
+            EnvSource::Breadcrumb => write!(f, "breadcrumb"),
+        }
+    }
+}
+
+/// How a run environment variable was computed, as shown by `run --dry-run`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvEntry {
+    pub value: String,
+    pub source: EnvSource,
+    // other sources that defined the variable, but lost to this one
+    pub overrides: Vec<EnvSource>,
+    pub removed: bool,
+    pub collision: bool,
+}
+
+impl EnvEntry {
+    fn new(value: String, source: EnvSource) -> Self {
+        Self {
+            value,
+            source,
+            overrides: vec![],
+            removed: false,
+            collision: false,
+        }
+    }
+
+    /// Replaces the value with one from a higher precedence source.
+    fn replace(&mut self, value: String, source: EnvSource) {
+        self.overrides.push(self.source);
+        self.value = value;
+        self.source = source;
+    }
+}
+
+pub type EnvReport = BTreeMap<String, EnvEntry>;
+
 pub type SubProcessResult<T> = std::result::Result<T, SubProcessError>;
 
 #[derive(Debug)]
 pub enum SubProcessError {
     EnvironmentCollisions(Vec<String>),
+    ProcessExecError(io::Error),
     ProcessRunError(PopenError),
+    ProcessSignalError(io::Error),
     ProcessOutputError(ErrReport),
     StrictRunError(String),
 }
@@ -63,9 +121,15 @@ impl fmt::Display for SubProcessError {
                     collisions.join(", ")
                 )
             }
+            SubProcessError::ProcessExecError(e) => {
+                write!(f, "Process exec error: {e}")
+            }
             SubProcessError::ProcessRunError(e) => {
                 write!(f, "Process run error: {e}")
             }
+            SubProcessError::ProcessSignalError(e) => {
+                write!(f, "Process signal error: {e}")
+            }
             SubProcessError::ProcessOutputError(e) => {
                 write!(f, "Problem writing output: {e}")
             }
@@ -94,6 +158,8 @@ impl From<ErrReport> for SubProcessError {
 pub struct SubProcess {
     ct_vars: EnvSettings,
     env_vars: EnvSettings,
+    report: EnvReport,
+    masked: Vec<String>,
 }
 
 impl SubProcess {
@@ -101,6 +167,8 @@ impl SubProcess {
         Self {
             ct_vars: default(),
             env_vars: default(),
+            report: default(),
+            masked: vec![],
         }
     }
 
@@ -108,6 +176,12 @@ impl SubProcess {
         self.ct_vars = ct_vars;
     }
 
+    /// Sets the values that are masked in the command output. When there are none, the output is
+    /// not piped through the CLI.
+    pub fn set_masked_values(&mut self, values: Vec<String>) {
+        self.masked = values;
+    }
+
     fn current_env(&self) -> EnvSettings {
         // Create a EnvSettings from the current set of environment variables (excluding a few).
         let exclude = ["PS1", "TERM"];
@@ -139,79 +213,126 @@ impl SubProcess {
         removals: &[String],
         strict: bool,
     ) -> SubProcessResult<()> {
-        self.env_vars = if inherit == Inheritance::None {
-            EnvSettings::new()
-        } else {
-            self.current_env()
-        };
+        // The report records the same steps as the `env_vars`, along with where each value
+        // came from.
+        self.report = EnvReport::new();
+        if inherit != Inheritance::None {
+            for (key, value) in self.current_env() {
+                self.report
+                    .insert(key, EnvEntry::new(value, EnvSource::Shell));
+            }
+        }
 
         // Add breadcrumbs about which environment.
-        self.env_vars.insert(
-            CT_ENVIRONMENT.to_string(),
-            resolved.environment_display_name().to_string(),
-        );
-        self.env_vars.insert(
-            CT_PROJECT.to_string(),
-            resolved.project_display_name().to_string(),
-        );
+        let breadcrumbs = [
+            (CT_ENVIRONMENT, resolved.environment_display_name()),
+            (CT_PROJECT, resolved.project_display_name()),
+        ];
+        for (key, value) in breadcrumbs {
+            self.set_report_value(key, value, EnvSource::Breadcrumb);
+        }
 
         // Add in the items from the CloudTruth environment (looking for collisions)
         let mut collisions: Vec<String> = vec![];
-        let empty = "".to_string();
         for (k, v) in &self.ct_vars {
             let key = k.clone();
             let value = v.clone();
             if strict && value == "-" {
                 return Err(SubProcessError::StrictRunError(key));
             }
-            if !self.env_vars.contains_key(&key) {
+            match self.report.get_mut(&key) {
                 // when not already, insert it
-                self.env_vars.entry(key).or_insert(value);
-            } else {
-                let orig = self.env_vars.get(&key).unwrap_or(&empty);
-                if inherit == Inheritance::Exclusive && value != *orig {
-                    collisions.push(key);
-                } else if inherit == Inheritance::Overlay {
-                    self.env_vars.insert(key, value);
+                None => {
+                    self.report
+                        .insert(key, EnvEntry::new(value, EnvSource::CloudTruth));
+                }
+                Some(entry) => {
+                    if inherit == Inheritance::Exclusive && value != entry.value {
+                        entry.collision = true;
+                        collisions.push(key);
+                    } else if inherit == Inheritance::Overlay {
+                        entry.replace(value, EnvSource::CloudTruth);
+                    } else if value != entry.value {
+                        // if doing Underlay, the local environment value is already set
+                        entry.overrides.push(EnvSource::CloudTruth);
+                    }
                 }
-                // if doing Underlay, the local environment value is already set
             }
         }
 
         // Add in the items from the overrides (looking for collisions)
         let over_vars = self.process_overrides(overrides)?;
         for (key, value) in over_vars {
-            let orig = self.env_vars.get(&key).unwrap_or(&empty);
-            if inherit == Inheritance::Exclusive
-                && self.env_vars.contains_key(&key)
-                && value != *orig
-            {
-                collisions.push(key);
-            } else {
+            match self.report.get_mut(&key) {
+                Some(entry) if inherit == Inheritance::Exclusive && value != entry.value => {
+                    entry.collision = true;
+                    collisions.push(key);
+                }
                 // use the "set" value as the final answer, when not worrying about collisions
-                self.env_vars.insert(key, value);
+                _ => self.set_report_value(&key, &value, EnvSource::Override),
             }
         }
 
+        // Remove the specified values.
+        for r in removals {
+            self.remove_variable(r);
+        }
+        self.update_env_vars();
+
         // return the error(s) if there were not any collisions
         if !collisions.is_empty() {
             Err(SubProcessError::EnvironmentCollisions(collisions))
         } else {
-            // Remove the specified values.
-            for r in removals {
-                self.env_vars.remove(r.as_str());
-            }
             Ok(())
         }
     }
 
+    fn set_report_value(&mut self, key: &str, value: &str, source: EnvSource) {
+        match self.report.get_mut(key) {
+            Some(entry) => entry.replace(value.to_string(), source),
+            None => {
+                self.report
+                    .insert(key.to_string(), EnvEntry::new(value.to_string(), source));
+            }
+        }
+    }
+
+    fn remove_variable(&mut self, key: &str) {
+        if let Some(entry) = self.report.get_mut(key) {
+            entry.removed = true;
+        }
+    }
+
+    /// Sets the sub-process environment from the report.
+    fn update_env_vars(&mut self) {
+        self.env_vars = self
+            .report
+            .iter()
+            .filter(|(_, entry)| !entry.removed)
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect();
+    }
+
+    /// Describes how each variable of the environment was computed by `set_environment()`,
+    /// including the removed variables.
+    pub fn environment_report(&self) -> &EnvReport {
+        &self.report
+    }
+
+    /// Adds a variable to the sub-process environment, after `set_environment()`.
+    pub fn set_variable(&mut self, key: &str, value: &str) {
+        self.set_report_value(key, value, EnvSource::Breadcrumb);
+        self.update_env_vars();
+    }
+
     pub fn remove_ct_app_vars(&mut self) {
         for app_var in CT_APP_REMOVABLE_VARS {
-            self.env_vars.remove(*app_var);
+            self.remove_variable(app_var);
         }
+        self.update_env_vars();
     }
 
-    pub fn run_command(&self, command: &str, arguments: &[String]) -> SubProcessResult<()> {
+    fn build_command(&self, command: &str, arguments: &[String]) -> Exec {
         let mut sub_proc: Exec;
 
         if arguments.is_empty() {
@@ -225,12 +346,296 @@ impl SubProcess {
         for (key, value) in &self.env_vars {
             sub_proc = sub_proc.env(key, value);
         }
+        if !self.masked.is_empty() {
+            sub_proc = sub_proc.stdout(Redirection::Pipe).stderr(Redirection::Pipe);
+        }
+        sub_proc
+    }
 
-        sub_proc.join()?;
-        Ok(())
+    /// Starts the command without waiting for it to finish.
+    ///
+    /// While the child is running, SIGINT, SIGTERM and SIGHUP sent to the CLI are passed along
+    /// to the child (on Unix).
+    pub fn spawn_command(
+        &self,
+        command: &str,
+        arguments: &[String],
+    ) -> SubProcessResult<ChildProcess> {
+        let mut popen = self.build_command(command, arguments).popen()?;
+        let forwarder = SignalForwarder::start(popen.pid());
+        let mut maskers = vec![];
+        if let Some(stdout) = popen.stdout.take() {
+            let redactor = Redactor::new(&self.masked);
+            maskers.push(thread::spawn(move || {
+                copy_redacted(stdout, io::stdout(), redactor)
+            }));
+        }
+        if let Some(stderr) = popen.stderr.take() {
+            let redactor = Redactor::new(&self.masked);
+            maskers.push(thread::spawn(move || {
+                copy_redacted(stderr, io::stderr(), redactor)
+            }));
+        }
+        Ok(ChildProcess {
+            popen,
+            forwarder: Some(forwarder),
+            maskers,
+        })
+    }
+
+    /// Runs the command to completion, and returns the exit code to use for the CLI.
+    pub fn run_command(&self, command: &str, arguments: &[String]) -> SubProcessResult<i32> {
+        self.spawn_command(command, arguments)?.wait()
+    }
+
+    /// Replaces the CLI process with the command, so the command receives signals directly and
+    /// its exit status is reported to the caller. Only returns when the exec fails.
+    #[cfg(unix)]
+    pub fn exec_command(&self, command: &str, arguments: &[String]) -> SubProcessError {
+        use std::os::unix::process::CommandExt;
+
+        let mut cmd: std::process::Command;
+        if arguments.is_empty() {
+            // same as the `Exec::shell()` used by run_command()
+            cmd = std::process::Command::new("sh");
+            cmd.arg("-c").arg(command);
+        } else {
+            cmd = std::process::Command::new(command);
+            cmd.args(arguments);
+        }
+        cmd.env_clear().envs(&self.env_vars);
+        SubProcessError::ProcessExecError(cmd.exec())
     }
 }
 
+/// A running child process, started by `SubProcess::spawn_command()`.
+pub struct ChildProcess {
+    popen: Popen,
+    forwarder: Option<SignalForwarder>,
+    // threads copying the masked output of the child
+    maskers: Vec<JoinHandle<io::Result<()>>>,
+}
+
+impl ChildProcess {
+    fn finished(&mut self, status: ExitStatus) -> i32 {
+        if let Some(forwarder) = self.forwarder.take() {
+            forwarder.stop();
+        }
+        // the output is complete once the child's end of the pipes is closed
+        for masker in self.maskers.drain(..) {
+            if let Ok(Err(e)) = masker.join() {
+                warn_user(format!("Problem writing the masked output: {e}"));
+            }
+        }
+        exit_code(status)
+    }
+
+    /// Waits for the child to exit, and returns the exit code to use for the CLI.
+    pub fn wait(mut self) -> SubProcessResult<i32> {
+        let status = self.popen.wait()?;
+        Ok(self.finished(status))
+    }
+
+    /// Waits up to `timeout` for the child to exit. Returns `None` when it is still running.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> SubProcessResult<Option<i32>> {
+        match self.popen.wait_timeout(timeout)? {
+            Some(status) => Ok(Some(self.finished(status))),
+            None => Ok(None),
+        }
+    }
+
+    /// Asks the child to exit with `signal`, and kills it if it is still running after the
+    /// `grace` period. On Windows, the child is terminated without a signal.
+    pub fn stop(mut self, signal: i32, grace: Duration) -> SubProcessResult<i32> {
+        #[cfg(unix)]
+        {
+            use subprocess::unix::PopenExt;
+            self.popen
+                .send_signal(signal)
+                .map_err(SubProcessError::ProcessSignalError)?;
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = signal;
+            self.popen
+                .terminate()
+                .map_err(SubProcessError::ProcessSignalError)?;
+        }
+        if let Some(code) = self.wait_timeout(grace)? {
+            return Ok(code);
+        }
+        warn_user(format!(
+            "Process did not exit within {} seconds, killing it.",
+            grace.as_secs_f32()
+        ));
+        self.popen
+            .kill()
+            .map_err(SubProcessError::ProcessSignalError)?;
+        self.wait()
+    }
+}
+
+/// Converts a signal name (e.g. `TERM` or `SIGTERM`) to its number.
+#[cfg(unix)]
+pub fn signal_from_name(name: &str) -> Option<i32> {
+    let upper = name.to_uppercase();
+    match upper.strip_prefix("SIG").unwrap_or(&upper) {
+        "HUP" => Some(libc::SIGHUP),
+        "INT" => Some(libc::SIGINT),
+        "QUIT" => Some(libc::SIGQUIT),
+        "KILL" => Some(libc::SIGKILL),
+        "USR1" => Some(libc::SIGUSR1),
+        "USR2" => Some(libc::SIGUSR2),
+        "TERM" => Some(libc::SIGTERM),
+        _ => None,
+    }
+}
+
+/// Signals are not used to stop processes on Windows, so the name is only checked.
+#[cfg(not(unix))]
+pub fn signal_from_name(name: &str) -> Option<i32> {
+    let upper = name.to_uppercase();
+    match upper.strip_prefix("SIG").unwrap_or(&upper) {
+        "HUP" | "INT" | "QUIT" | "KILL" | "USR1" | "USR2" | "TERM" => Some(0),
+        _ => None,
+    }
+}
+
+/// Merges the values from each layer in order, so later layers take precedence. The keys that
+/// have different values in two layers are returned, so the overrides can be reported.
+///
+/// When `strict`, a key with different values in two layers is a collision instead.
+pub fn merge_layers(
+    layers: Vec<EnvSettings>,
+    strict: bool,
+) -> SubProcessResult<(EnvSettings, Vec<String>)> {
+    let mut merged = EnvSettings::new();
+    let mut collisions: Vec<String> = vec![];
+    for layer in layers {
+        for (key, value) in layer {
+            if let Some(orig) = merged.get(&key) {
+                if *orig != value && !collisions.contains(&key) {
+                    collisions.push(key.clone());
+                }
+            }
+            merged.insert(key, value);
+        }
+    }
+    collisions.sort();
+    if strict && !collisions.is_empty() {
+        Err(SubProcessError::EnvironmentCollisions(collisions))
+    } else {
+        Ok((merged, collisions))
+    }
+}
+
+/// Computes a hash for each value, so changes can be detected without holding onto the values.
+pub fn value_fingerprints(vars: &EnvSettings) -> BTreeMap<String, String> {
+    vars.iter()
+        .map(|(k, v)| (k.clone(), hex::encode(Sha256::digest(v.as_bytes()))))
+        .collect()
+}
+
+/// Lists the keys that were added, removed or have different values.
+pub fn changed_keys(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter(|k| before.get(*k) != after.get(*k))
+        .cloned()
+        .collect()
+}
+
+/// Maps the child's exit status to a shell-style exit code (128 + signal for signalled children).
+fn exit_code(status: ExitStatus) -> i32 {
+    match status {
+        ExitStatus::Exited(code) => code as i32,
+        ExitStatus::Signaled(signal) => 128 + signal as i32,
+        ExitStatus::Other(code) => code,
+        ExitStatus::Undetermined => 1,
+    }
+}
+
+/// The child the signals are forwarded to, or 0 when no child is running.
+#[cfg(unix)]
+static FORWARD_PID: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+/// Forwards the signals the CLI receives to the running child.
+///
+/// The signal handlers cannot be uninstalled, so the forwarding thread is started once and
+/// pointed at each new child (e.g. when `--watch` restarts the command). Without a child, the
+/// signal gets its default action, so the CLI can still be stopped.
+#[cfg(unix)]
+struct SignalForwarder {}
+
+#[cfg(unix)]
+impl SignalForwarder {
+    fn start(child_pid: Option<u32>) -> Self {
+        use std::sync::atomic::Ordering;
+
+        static STARTED: once_cell::sync::OnceCell<()> = once_cell::sync::OnceCell::new();
+        if let Some(pid) = child_pid {
+            FORWARD_PID.store(pid as i32, Ordering::SeqCst);
+            STARTED.get_or_init(Self::forward_signals);
+        }
+        Self {}
+    }
+
+    fn forward_signals() {
+        use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+        use signal_hook::iterator::Signals;
+        use std::sync::atomic::Ordering;
+
+        let mut signals = match Signals::new([SIGINT, SIGTERM, SIGHUP]) {
+            Ok(signals) => signals,
+            Err(e) => {
+                warn_user(format!("Signals will not be forwarded to the command: {e}"));
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            for signal in signals.forever() {
+                let pid = FORWARD_PID.load(Ordering::SeqCst);
+                if pid == 0 {
+                    let _ = signal_hook::low_level::emulate_default_handler(signal);
+                    continue;
+                }
+                // A Ctrl-C at the terminal is already delivered to the whole foreground
+                // process group (including the child), so avoid sending it twice.
+                if signal == SIGINT && in_foreground_group() {
+                    continue;
+                }
+                unsafe {
+                    libc::kill(pid as libc::pid_t, signal);
+                }
+            }
+        });
+    }
+
+    fn stop(self) {
+        FORWARD_PID.store(0, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(unix)]
+fn in_foreground_group() -> bool {
+    unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+#[cfg(not(unix))]
+struct SignalForwarder {}
+
+#[cfg(not(unix))]
+impl SignalForwarder {
+    fn start(_child_pid: Option<u32>) -> Self {
+        Self {}
+    }
+
+    fn stop(self) {}
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -247,6 +652,53 @@ mod test {
         }
     }
 
+    #[test]
+    fn environment_report_sources() {
+        env::set_var("CT_REPORT_SHELL", "shell");
+        env::set_var("CT_REPORT_BOTH", "shell");
+        let resolved = ResolvedDetails::new(
+            "env".to_string(),
+            "env-id".to_string(),
+            "proj".to_string(),
+            "proj-id".to_string(),
+        );
+        let mut ct_vars = EnvSettings::new();
+        ct_vars.insert("CT_REPORT_BOTH".to_string(), "cloudtruth".to_string());
+        ct_vars.insert("CT_REPORT_ONLY".to_string(), "cloudtruth".to_string());
+        let overrides = vec!["CT_REPORT_ONLY=set".to_string()];
+        let removals = vec!["CT_REPORT_SHELL".to_string()];
+
+        let mut sub_proc = SubProcess::new();
+        sub_proc.set_cloudtruth_environment(ct_vars.clone());
+        sub_proc
+            .set_environment(
+                &resolved,
+                Inheritance::Overlay,
+                &overrides,
+                &removals,
+                false,
+            )
+            .unwrap();
+        let report = sub_proc.environment_report();
+        let both = &report["CT_REPORT_BOTH"];
+        assert_eq!(both.source, EnvSource::CloudTruth);
+        assert_eq!(both.overrides, vec![EnvSource::Shell]);
+        let only = &report["CT_REPORT_ONLY"];
+        assert_eq!(only.source, EnvSource::Override);
+        assert_eq!(only.overrides, vec![EnvSource::CloudTruth]);
+        assert!(report["CT_REPORT_SHELL"].removed);
+        assert!(!sub_proc.env_vars.contains_key("CT_REPORT_SHELL"));
+        assert_eq!(report[CT_PROJECT].source, EnvSource::Breadcrumb);
+
+        let result = sub_proc.set_environment(&resolved, Inheritance::Exclusive, &[], &[], false);
+        assert!(matches!(
+            result,
+            Err(SubProcessError::EnvironmentCollisions(_))
+        ));
+        assert!(sub_proc.environment_report()["CT_REPORT_BOTH"].collision);
+        assert_eq!(sub_proc.env_vars["CT_REPORT_BOTH"], "shell");
+    }
+
     #[test]
     fn inherit_from_string() {
         // Tests case insensitivity, as well as all possible versions
@@ -262,4 +714,82 @@ mod test {
             assert_eq!(Inheritance::from_str(sv.as_str()), iv);
         }
     }
+
+    #[test]
+    fn exit_code_from_status() {
+        assert_eq!(exit_code(ExitStatus::Exited(0)), 0);
+        assert_eq!(exit_code(ExitStatus::Exited(3)), 3);
+        assert_eq!(exit_code(ExitStatus::Signaled(2)), 130);
+        assert_eq!(exit_code(ExitStatus::Signaled(15)), 143);
+        assert_eq!(exit_code(ExitStatus::Undetermined), 1);
+    }
+
+    #[test]
+    fn fingerprint_changes() {
+        let mut before = EnvSettings::new();
+        before.insert("SAME".to_string(), "value".to_string());
+        before.insert("CHANGED".to_string(), "old".to_string());
+        before.insert("REMOVED".to_string(), "gone".to_string());
+        let mut after = EnvSettings::new();
+        after.insert("SAME".to_string(), "value".to_string());
+        after.insert("CHANGED".to_string(), "new".to_string());
+        after.insert("ADDED".to_string(), "here".to_string());
+
+        let before = value_fingerprints(&before);
+        let after = value_fingerprints(&after);
+        assert!(!before.values().any(|v| v == "old" || v == "value"));
+        assert_eq!(
+            changed_keys(&before, &after),
+            vec!["ADDED", "CHANGED", "REMOVED"]
+        );
+        assert!(changed_keys(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn layer_precedence_and_collisions() {
+        let layer = |pairs: &[(&str, &str)]| -> EnvSettings {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let layers = vec![
+            layer(&[("SHARED", "platform"), ("SAME", "1"), ("BASE", "b")]),
+            layer(&[("SHARED", "service"), ("SAME", "1"), ("TOP", "t")]),
+        ];
+        let (merged, overridden) = merge_layers(layers.clone(), false).unwrap();
+        assert_eq!(merged.get("SHARED").unwrap(), "service");
+        assert_eq!(merged.len(), 4);
+        assert_eq!(overridden, vec!["SHARED"]);
+
+        match merge_layers(layers, true) {
+            Err(SubProcessError::EnvironmentCollisions(keys)) => assert_eq!(keys, vec!["SHARED"]),
+            _ => panic!("expected a collision"),
+        }
+
+        // the same value in several layers is not an override
+        let layers = vec![layer(&[("SAME", "1")]), layer(&[("SAME", "1")])];
+        let (merged, overridden) = merge_layers(layers, true).unwrap();
+        assert_eq!(merged.len(), 1);
+        assert!(overridden.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_exit_code() {
+        let mut sub_proc = SubProcess::new();
+        sub_proc
+            .env_vars
+            .insert("PATH".to_string(), env::var("PATH").unwrap_or_default());
+        assert_eq!(sub_proc.run_command("exit 3", &[]).unwrap(), 3);
+        assert_eq!(sub_proc.run_command("kill -TERM $$", &[]).unwrap(), 143);
+        let args = vec!["-c".to_string(), "exit 0".to_string()];
+        assert_eq!(sub_proc.run_command("sh", &args).unwrap(), 0);
+
+        // run without a shell, so the signal is not deferred until the sleep completes
+        let args = vec!["5".to_string()];
+        let child = sub_proc.spawn_command("sleep", &args).unwrap();
+        let signal = signal_from_name("int").unwrap();
+        assert_eq!(child.stop(signal, Duration::from_secs(2)).unwrap(), 130);
+    }
 }
@@ -4,12 +4,14 @@ use crate::cli::{
     SECRETS_FLAG, SET_SUBCMD, SHOW_TIMES_FLAG, TEMPLATE_FILE_OPT,
 };
 use crate::database::{HistoryAction, OpenApiConfig, ResolvedDetails, TemplateHistory, Templates};
+use crate::offline_cache::{cached_fetch, OfflineCache};
 use crate::table::Table;
 use crate::utils::{
     error_message, parse_datetime, parse_tag, user_confirm, warn_missing_subcommand,
     warning_message, DEL_CONFIRM, FILE_READ_ERR,
 };
 use clap::ArgMatches;
+use cloudtruth_config::Config;
 use color_eyre::eyre::Result;
 use similar::TextDiff;
 use std::fs;
@@ -137,6 +139,7 @@ fn proc_template_get(
     rest_cfg: &OpenApiConfig,
     templates: &Templates,
     resolved: &ResolvedDetails,
+    config: &Config,
 ) -> Result<()> {
     let proj_name = resolved.project_display_name();
     let template_name = subcmd_args.value_of(NAME_ARG).unwrap();
@@ -146,22 +149,37 @@ fn proc_template_get(
     let raw = subcmd_args.is_present(RAW_FLAG);
     let proj_id = resolved.project_id();
     let env_name = resolved.environment_display_name().to_string();
-
-    let details = templates.get_details_by_name(
-        rest_cfg,
-        proj_name,
+    let cache = OfflineCache::new(config);
+    let key = [
+        "template",
         proj_id,
+        resolved.environment_id(),
         template_name,
-        !raw,
-        show_secrets,
-        Some(env_name),
-        as_of,
-        tag,
-    )?;
-    if details.body.ends_with('\n') {
-        print!("{}", details.body);
+        &raw.to_string(),
+        &show_secrets.to_string(),
+        as_of.as_deref().unwrap_or_default(),
+        tag.as_deref().unwrap_or_default(),
+    ];
+
+    let body = cached_fetch(cache.as_ref(), &key, || {
+        templates
+            .get_details_by_name(
+                rest_cfg,
+                proj_name,
+                proj_id,
+                template_name,
+                !raw,
+                show_secrets,
+                Some(env_name),
+                as_of.clone(),
+                tag.clone(),
+            )
+            .map(|details| details.body)
+    })?;
+    if body.ends_with('\n') {
+        print!("{body}");
     } else {
-        println!("{}", details.body);
+        println!("{body}");
     }
     Ok(())
 }
@@ -468,6 +486,7 @@ pub fn process_templates_command(
     subcmd_args: &ArgMatches,
     rest_cfg: &OpenApiConfig,
     resolved: &ResolvedDetails,
+    config: &Config,
 ) -> Result<()> {
     let templates = Templates::new();
     if let Some(subcmd_args) = subcmd_args.subcommand_matches(DELETE_SUBCMD) {
@@ -479,7 +498,7 @@ pub fn process_templates_command(
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(LIST_SUBCMD) {
         proc_template_list(subcmd_args, rest_cfg, &templates, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(GET_SUBCMD) {
-        proc_template_get(subcmd_args, rest_cfg, &templates, resolved)?;
+        proc_template_get(subcmd_args, rest_cfg, &templates, resolved, config)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches("preview") {
         proc_template_preview(subcmd_args, rest_cfg, &templates, resolved)?;
     } else if let Some(subcmd_args) = subcmd_args.subcommand_matches(SET_SUBCMD) {
@@ -1,3 +1,4 @@
+use crate::database::{decrypt_envelopes, CryptoEnvelope};
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use color_eyre::eyre::Result;
 use color_eyre::Report;
@@ -5,8 +6,11 @@ use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::fmt::Formatter;
+use std::fs;
 use std::io::{stdin, stdout, Write};
+use std::process;
 use std::str;
+use std::time::Duration;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 // The `DEL_CONFIRM` is the default value for delete confirmation across different types
@@ -214,6 +218,29 @@ pub fn parse_tag(input: Option<&str>) -> Option<String> {
     }
 }
 
+/// Parses a duration like `500ms`, `30s`, `5m` or `1h`. A number without units is in seconds.
+/// Zero and durations that overflow are rejected.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, units) = input.split_at(split);
+    let count: u64 = number.parse().ok()?;
+    let duration = match units.trim() {
+        "ms" => Duration::from_millis(count),
+        "" | "s" | "sec" => Duration::from_secs(count),
+        "m" | "min" => Duration::from_secs(count.checked_mul(60)?),
+        "h" => Duration::from_secs(count.checked_mul(60 * 60)?),
+        _ => return None,
+    };
+    if duration.is_zero() {
+        None
+    } else {
+        Some(duration)
+    }
+}
+
 /// Return the default value of a type according to the `Default` trait.
 ///
 /// The type to return is inferred from context; this is equivalent to
@@ -225,6 +252,36 @@ pub fn default<T: Default>() -> T {
     Default::default()
 }
 
+/// Reads the key material for client-side encrypted parameter values, when a key file is
+/// configured.
+pub fn encryption_key(key_file: Option<&str>) -> Option<Vec<u8>> {
+    let filename = key_file?;
+    match fs::read(filename) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            error_message(format!(
+                "Failed to read encryption key file '{filename}': {e}"
+            ));
+            process::exit(59);
+        }
+    }
+}
+
+/// Decrypts a client-side encrypted parameter value. Without a `key`, the value is returned as-is.
+pub fn decrypt_value(name: &str, value: &str, key: Option<&[u8]>) -> Result<String> {
+    match key {
+        Some(key) => Ok(decrypt_envelopes(value, key, |s| s.to_string())?),
+        None => {
+            if CryptoEnvelope::from_value(value).is_some() {
+                warning_message(format!(
+                    "The '{name}' value is encrypted, but no encryption key file is configured"
+                ));
+            }
+            Ok(value.to_string())
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -297,4 +354,20 @@ mod test {
         assert_eq!(parse_datetime(None), None);
         assert_eq!(parse_tag(None), None);
     }
+
+    #[test]
+    fn duration_parsing() {
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration(" 2h "), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("-5s"), None);
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("0ms"), None);
+        assert_eq!(parse_duration(&format!("{}h", u64::MAX)), None);
+        assert_eq!(parse_duration(&format!("{}m", u64::MAX / 2)), None);
+    }
 }